@@ -0,0 +1,309 @@
+//! Parsing a subset of ANSI / VT100 escape sequences.
+
+/// The numeric parameters of a control sequence.
+///
+/// At most 8 parameters are kept, and any others are ignored. A parameter
+/// that was left empty (such as both parameters in `ESC[;H`) is 0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AnsiParams {
+  values: [u16; 8],
+  len: u8,
+}
+impl AnsiParams {
+  #[inline]
+  #[must_use]
+  pub fn as_slice(&self) -> &[u16] {
+    &self.values[..self.len as usize]
+  }
+  /// Gets parameter `i`, using `default` if it's missing or 0.
+  #[inline]
+  #[must_use]
+  pub fn get_or(&self, i: usize, default: u16) -> u16 {
+    match self.as_slice().get(i).copied() {
+      None | Some(0) => default,
+      Some(n) => n,
+    }
+  }
+}
+
+/// Something that the parser found in the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnsiEvent {
+  /// A normal character to draw.
+  Print(char),
+  /// A C0 control code (other than `ESC`), such as `\n`.
+  Control(u8),
+  /// `ESC[nA`, move up `n` rows.
+  CursorUp(u16),
+  /// `ESC[nB`, move down `n` rows.
+  CursorDown(u16),
+  /// `ESC[nC`, move right `n` columns.
+  CursorForward(u16),
+  /// `ESC[nD`, move left `n` columns.
+  CursorBack(u16),
+  /// `ESC[row;colH` (or `f`), move to a position. These are 0-based, unlike
+  /// in the escape sequence itself.
+  CursorPosition { row: u16, col: u16 },
+  /// `ESC[nJ`, 0 erases to the end of the display, 1 erases to the start of
+  /// the display, and 2 (or 3) erases all of it.
+  EraseInDisplay(u16),
+  /// `ESC[nK`, 0 erases to the end of the line, 1 erases to the start of the
+  /// line, and 2 erases all of it.
+  EraseInLine(u16),
+  /// `ESC[...m`, set colors and other attributes.
+  SelectGraphicRendition(AnsiParams),
+  /// `ESC7` or `ESC[s`.
+  SaveCursor,
+  /// `ESC8` or `ESC[u`.
+  RestoreCursor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AnsiState {
+  Ground,
+  Escape,
+  Csi,
+  /// A CSI sequence we don't understand, which is skipped until the final
+  /// byte.
+  CsiIgnore,
+}
+
+/// A state machine that turns characters into [`AnsiEvent`]s.
+///
+/// Feed it one character at a time. Sequences outside of the supported
+/// subset are quietly dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnsiParser {
+  state: AnsiState,
+  params: AnsiParams,
+}
+impl AnsiParser {
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      state: AnsiState::Ground,
+      params: AnsiParams { values: [0; 8], len: 0 },
+    }
+  }
+
+  /// Processes one character, maybe giving back an event.
+  #[must_use]
+  pub fn advance(&mut self, c: char) -> Option<AnsiEvent> {
+    match self.state {
+      AnsiState::Ground => match c {
+        '\x1B' => {
+          self.state = AnsiState::Escape;
+          None
+        }
+        '\0'..='\x1F' => Some(AnsiEvent::Control(c as u8)),
+        _ => Some(AnsiEvent::Print(c)),
+      },
+      AnsiState::Escape => {
+        self.state = AnsiState::Ground;
+        match c {
+          '[' => {
+            self.params = AnsiParams::default();
+            self.state = AnsiState::Csi;
+            None
+          }
+          '7' => Some(AnsiEvent::SaveCursor),
+          '8' => Some(AnsiEvent::RestoreCursor),
+          _ => None,
+        }
+      }
+      AnsiState::Csi => match c {
+        '0'..='9' => {
+          if self.params.len == 0 {
+            self.params.len = 1;
+          }
+          let i = self.params.len as usize - 1;
+          if let Some(v) = self.params.values.get_mut(i) {
+            *v = v.saturating_mul(10).saturating_add(c as u16 - '0' as u16);
+          }
+          None
+        }
+        ';' => {
+          if self.params.len == 0 {
+            self.params.len = 1;
+          }
+          self.params.len = self.params.len.saturating_add(1);
+          None
+        }
+        '\x40'..='\x7E' => {
+          self.state = AnsiState::Ground;
+          self.params.len = self.params.len.min(8);
+          self.dispatch(c)
+        }
+        _ => {
+          self.state = AnsiState::CsiIgnore;
+          None
+        }
+      },
+      AnsiState::CsiIgnore => {
+        if matches!(c, '\x40'..='\x7E') {
+          self.state = AnsiState::Ground;
+        }
+        None
+      }
+    }
+  }
+
+  #[inline]
+  #[must_use]
+  fn dispatch(&self, final_char: char) -> Option<AnsiEvent> {
+    let p = &self.params;
+    Some(match final_char {
+      'A' => AnsiEvent::CursorUp(p.get_or(0, 1)),
+      'B' => AnsiEvent::CursorDown(p.get_or(0, 1)),
+      'C' => AnsiEvent::CursorForward(p.get_or(0, 1)),
+      'D' => AnsiEvent::CursorBack(p.get_or(0, 1)),
+      'H' | 'f' => AnsiEvent::CursorPosition {
+        row: p.get_or(0, 1) - 1,
+        col: p.get_or(1, 1) - 1,
+      },
+      'J' => AnsiEvent::EraseInDisplay(p.get_or(0, 0)),
+      'K' => AnsiEvent::EraseInLine(p.get_or(0, 0)),
+      'm' => AnsiEvent::SelectGraphicRendition(*p),
+      's' => AnsiEvent::SaveCursor,
+      'u' => AnsiEvent::RestoreCursor,
+      _ => return None,
+    })
+  }
+}
+impl Default for AnsiParser {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[cfg(all(test, not(target_arch = "arm")))]
+mod tests {
+  extern crate std;
+
+  use super::*;
+  use quickcheck::quickcheck;
+  use std::{string::String, vec, vec::Vec};
+
+  fn events(text: &str) -> Vec<AnsiEvent> {
+    let mut parser = AnsiParser::new();
+    text.chars().filter_map(|c| parser.advance(c)).collect()
+  }
+
+  fn sgr(text: &str) -> Vec<u16> {
+    match events(text).as_slice() {
+      [AnsiEvent::SelectGraphicRendition(params)] => params.as_slice().to_vec(),
+      other => panic!("expected one SGR event, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn plain_text_and_controls() {
+    assert_eq!(
+      events("a\n\tb"),
+      vec![
+        AnsiEvent::Print('a'),
+        AnsiEvent::Control(b'\n'),
+        AnsiEvent::Control(b'\t'),
+        AnsiEvent::Print('b'),
+      ]
+    );
+  }
+
+  #[test]
+  fn cursor_moves_default_to_one() {
+    assert_eq!(
+      events("\x1B[A\x1B[3B\x1B[0C\x1B[12D"),
+      vec![
+        AnsiEvent::CursorUp(1),
+        AnsiEvent::CursorDown(3),
+        AnsiEvent::CursorForward(1),
+        AnsiEvent::CursorBack(12),
+      ]
+    );
+  }
+
+  #[test]
+  fn cursor_position_is_zero_based() {
+    assert_eq!(
+      events("\x1B[5;10H\x1B[;H\x1B[2f"),
+      vec![
+        AnsiEvent::CursorPosition { row: 4, col: 9 },
+        AnsiEvent::CursorPosition { row: 0, col: 0 },
+        AnsiEvent::CursorPosition { row: 1, col: 0 },
+      ]
+    );
+  }
+
+  #[test]
+  fn erase_defaults_to_zero() {
+    assert_eq!(
+      events("\x1B[J\x1B[2J\x1B[K\x1B[1K"),
+      vec![
+        AnsiEvent::EraseInDisplay(0),
+        AnsiEvent::EraseInDisplay(2),
+        AnsiEvent::EraseInLine(0),
+        AnsiEvent::EraseInLine(1),
+      ]
+    );
+  }
+
+  #[test]
+  fn sgr_params() {
+    assert_eq!(sgr("\x1B[m"), Vec::<u16>::new());
+    assert_eq!(sgr("\x1B[1;31m"), vec![1, 31]);
+    assert_eq!(sgr("\x1B[;2m"), vec![0, 2]);
+    assert_eq!(sgr("\x1B[1;2;3;4;5;6;7;8;9;10m"), vec![1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(sgr("\x1B[99999m"), vec![u16::MAX]);
+  }
+
+  #[test]
+  fn save_and_restore() {
+    assert_eq!(
+      events("\x1B7\x1B[s\x1B8\x1B[u"),
+      vec![
+        AnsiEvent::SaveCursor,
+        AnsiEvent::SaveCursor,
+        AnsiEvent::RestoreCursor,
+        AnsiEvent::RestoreCursor,
+      ]
+    );
+  }
+
+  #[test]
+  fn unsupported_sequences_are_dropped() {
+    assert_eq!(events("\x1B[?25hx"), vec![AnsiEvent::Print('x')]);
+    assert_eq!(events("\x1B[5zx"), vec![AnsiEvent::Print('x')]);
+    assert_eq!(events("\x1BQx"), vec![AnsiEvent::Print('x')]);
+  }
+
+  quickcheck! {
+    fn text_without_escapes_is_one_event_per_char(text: String) -> bool {
+      let text: String = text.chars().filter(|&c| c != '\x1B').collect();
+      let expected: Vec<AnsiEvent> = text
+        .chars()
+        .map(|c| match c {
+          '\0'..='\x1F' => AnsiEvent::Control(c as u8),
+          _ => AnsiEvent::Print(c),
+        })
+        .collect();
+      events(&text) == expected
+    }
+
+    fn a_sequence_never_leaks_into_the_text(params: Vec<u8>) -> bool {
+      let mut text = String::from("\x1B[");
+      for p in params {
+        text.push(match p % 11 {
+          10 => ';',
+          digit => char::from(b'0' + digit),
+        });
+      }
+      text.push_str("mx");
+      matches!(
+        events(&text).as_slice(),
+        [AnsiEvent::SelectGraphicRendition(_), AnsiEvent::Print('x')]
+      )
+    }
+  }
+}
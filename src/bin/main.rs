@@ -6,87 +6,52 @@
 use core::{fmt::Write, mem::size_of_val};
 
 use bytemuck::{cast_slice, cast_slice_mut};
-use zygravan::{gba::*, Ewram};
+use zygravan::{
+  gba::{irq::IrqSource, *},
+  Align, Command, CommandError, CommandRegistry, DialogBox, DialogEvent, Ewram,
+  KeyState, LineEditor, OnScreenKeyboard, ProportionalFont, Scrollback,
+  TextCanvas, TextConsole, SOFT_RESET_CHORD,
+};
 
 #[panic_handler]
-fn panic_handler(_info: &core::panic::PanicInfo) -> ! {
-  PalRam::backdrop().write(Color::from_rgb(31, 0, 0));
-  loop {}
+fn panic_handler(info: &core::panic::PanicInfo) -> ! {
+  zygravan::panic_screen(info)
 }
 
+const VRAM_PLAN: VramPlan = VramPlan::new()
+  .with_tiles(BgCharblock::_0, 256 + 30, false)
+  .with_text_bg(
+    0,
+    BgCharblock::_0,
+    TextScreenblock::_8,
+    TextScreenSize::Tiles32x32,
+  )
+  .with_text_bg(
+    1,
+    BgCharblock::_0,
+    TextScreenblock::_9,
+    TextScreenSize::Tiles32x32,
+  );
+
+/// L+R+Select puts the GBA to sleep, and wakes it up again.
+const SLEEP_CHORD: Keys =
+  Keys::new().with_l(true).with_r(true).with_select(true);
+
 static VBLANK_COUNTER: GbaCell<u32> = GbaCell::new_u32(0);
 
-extern "C" fn irq_handler(bits: IrqBits) {
-  if bits.vblank() {
-    VBLANK_COUNTER.write(VBLANK_COUNTER.read().wrapping_add(1));
-  }
+fn on_vblank() {
+  VBLANK_COUNTER.write(VBLANK_COUNTER.read().wrapping_add(1));
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct TerminalPanel {
-  chars: [u32; (32 * 32) / 4],
-  banks: [u32; (32 * 32) / 4],
-  position: u32,
-}
-impl TerminalPanel {
-  #[inline]
-  #[must_use]
-  pub const fn new() -> Self {
-    Self {
-      chars: [0_u32; (32 * 32) / 4],
-      banks: [0_u32; (32 * 32) / 4],
-      position: 0,
-    }
-  }
-  #[inline]
-  pub fn set_all_chars(&mut self, ch: char) {
-    let b = ch as u8;
-    let u = u32::from_ne_bytes([b, b, b, b]);
-    self.chars.iter_mut().for_each(|ch| *ch = u);
-  }
-  #[inline]
-  pub fn set_all_banks(&mut self, b: u8) {
-    let u = u32::from_ne_bytes([b, b, b, b]);
-    self.banks.iter_mut().for_each(|bank| *bank = u);
-  }
-  #[inline]
-  pub fn change_line(&mut self, delta: i32) {
-    self.position =
-      ((self.position >> 5).wrapping_add(delta as u32) << 5) % (32 * 32);
-  }
-  #[inline]
-  pub fn push_bytes(&mut self, bytes: &[u8]) {
-    for byte in bytes.iter().copied() {
-      match byte {
-        b'\n' => self.change_line(1),
-        other => {
-          let bytes: &mut [u8] = cast_slice_mut::<u32, u8>(&mut self.chars);
-          bytes[self.position as usize] = other;
-          self.position += 1;
-          if (self.position % 32) == 30 {
-            self.change_line(1);
-          }
-        }
-      }
+fn cmd_echo(args: &[&str], out: &mut dyn Write) -> Result<(), CommandError> {
+  for (i, arg) in args[1..].iter().enumerate() {
+    if i > 0 {
+      write!(out, " ")?;
     }
+    write!(out, "{}", arg)?;
   }
-  #[inline]
-  #[must_use]
-  pub fn scroll_point(&self) -> u16 {
-    0_u16.wrapping_sub((19 - (self.position >> 5) as u16) * 8)
-  }
-}
-impl core::fmt::Write for TerminalPanel {
-  #[inline]
-  fn write_str(&mut self, s: &str) -> core::fmt::Result {
-    self.push_bytes(s.as_bytes());
-    Ok(())
-  }
-  #[inline]
-  fn write_char(&mut self, c: char) -> core::fmt::Result {
-    self.push_bytes(core::slice::from_ref(&(c as u8)));
-    Ok(())
-  }
+  writeln!(out)?;
+  Ok(())
 }
 
 #[no_mangle]
@@ -94,12 +59,6 @@ impl core::fmt::Write for TerminalPanel {
 pub extern "C" fn main() -> ! {
   //
 
-  if let Some(mut ewram) = Ewram::try_new() {
-    let ewram_bytes: &mut [u8] = cast_slice_mut(&mut *ewram);
-    let hello_world = b"HelloWorld";
-    ewram_bytes.iter_mut().zip(hello_world.iter()).for_each(|(e, b)| *e = *b);
-  }
-
   decompress_cp437_data_to(BgCharblock::_0.tiles4());
 
   // TODO: put some stuff into OBJ tile memory.
@@ -129,79 +88,115 @@ pub extern "C" fn main() -> ! {
   PalRam::bg_palbank(15).index(1).write(Color::DIM_WHITE);
 
   //
-  BG0CNT.write(BgControl::new().with_screenblock(8));
-
-  let mut panel = TerminalPanel::new();
-  //panel.set_all_chars('.');
+  // the dialog box goes on bg1, which is drawn over the console on bg0.
+  let bg0 = VRAM_PLAN.background(0).with_z_index(1);
+  bg0.write_control();
+  VRAM_PLAN.background(1).write_control();
+
+  let mut panel = TextConsole::<30, 15>::new(VRAM_PLAN.screenblock(0));
+  if let Some(ewram) = Ewram::try_new() {
+    panel = panel.with_scrollback(Scrollback::new(ewram));
+  }
+  for i in 0..40 {
+    writeln!(panel, "Scrollback line {}", i).unwrap();
+  }
   writeln!(panel, "Hello World!").unwrap();
   writeln!(panel, "Another line of text.").unwrap();
   writeln!(panel, "This line of text is in excess of thirty characters.")
     .unwrap();
+  writeln!(panel, "\x1B[31mRed\x1B[0m and \x1B[2;32mdim green\x1B[0m.")
+    .unwrap();
+  writeln!(panel, "╔══╗ ♥♦♣♠ αβγ").unwrap();
   write!(panel, ">").unwrap();
 
+  let shell = CommandRegistry::<8>::new()
+    .with_builtins()
+    .with_command(Command { name: "echo", usage: "[args...]", run: cmd_echo });
+  let mut editor = LineEditor::<28>::new();
+  let mut keyboard =
+    OnScreenKeyboard::new(VRAM_PLAN.screenblock(0)).with_origin(5, 16);
+
+  // a title line in proportional text, between the console and keyboard.
+  let font = ProportionalFont::from_tile4s(BgCharblock::_0.tiles4(), 4);
+  let title = TextCanvas::new(BgCharblock::_0, 256, 30, 1);
+  title.clear();
+  title.draw_wrapped(&font, b"Zygravan debug console", Align::Center, 8);
+  title.map_to(VRAM_PLAN.screenblock(0), 0, 15, 7);
+
+  let mut dialog = DialogBox::<26, 4>::new(VRAM_PLAN.screenblock(1))
+    .with_origin(1, 13)
+    .with_palbank(7);
+  dialog.start(
+    "Welcome to the {c6}debug console{c}.{p30} Type commands with the \
+     keyboard below, and press Start to run them.{page}Try {c3}help{c} \
+     first. Ready?",
+  );
+  dialog.set_speaker(Some("Zygravan"));
+  dialog.set_choices(&["Yes", "No"]);
+
   //
-  set_irq_handler(Some(irq_handler));
-  DISPSTAT.write(DisplayStatus::new().with_vblank_irq(true));
-  IE.write(IrqBits::new().with_vblank(true));
+  irq::register(IrqSource::VBlank, on_vblank);
+  irq::enable(IrqSource::VBlank);
   IME.write(true);
 
   // remove forced_blank, which will begin the display cycle.
-  DISPCNT.write(DisplayControl::new().with_display_bg0(true));
+  DISPCNT
+    .write(DisplayControl::new().with_display_bg0(true).with_display_bg1(true));
 
   let mut x_off = 0_u16;
   let mut y_off = 0_u16;
 
-  let mut last_k = Keys::default();
+  let mut keys = KeyState::new();
 
   // primary loop
   loop {
     // the user input
-    let k = get_keys();
-
-    // update world state
-    if k.right() {
-      x_off = x_off.wrapping_sub(1);
-    } else if k.left() {
-      x_off = x_off.wrapping_add(1);
-    }
-    /*
-    if k.down() {
-      y_off = y_off.wrapping_sub(1);
-    } else if k.up() {
-      y_off = y_off.wrapping_add(1);
+    keys.update();
+    if keys.chord(SOFT_RESET_CHORD) {
+      SoftReset();
     }
-    if k.l() & !last_k.l() {
-      y_off = y_off.wrapping_add(8);
+    if keys.chord(SLEEP_CHORD) {
+      sleep_until_keys(SLEEP_CHORD);
+      keys.update();
     }
-    if k.r() & !last_k.r() {
-      y_off = y_off.wrapping_sub(8);
+
+    // update world state
+    let pressed = keys.pressed();
+    if dialog.is_active() {
+      match dialog.update(pressed) {
+        Some(DialogEvent::Chosen(0)) => {
+          writeln!(panel, "\rGood luck!").unwrap()
+        }
+        Some(DialogEvent::Chosen(_)) => writeln!(panel, "\rOkay...").unwrap(),
+        _ => (),
+      }
+      if !dialog.is_active() {
+        dialog.close();
+        write!(panel, ">").unwrap();
+      }
+    } else if panel.view_offset() > 0
+      || (editor.is_empty() && (pressed.l() || pressed.r()))
+    {
+      panel.handle_view_keys(pressed);
+    } else if let Some(event) = keyboard.update(keys.repeated()) {
+      if let Some(line) = editor.apply_keyboard_event(event) {
+        writeln!(panel, "\r\x1B[K>{}", line).unwrap();
+        shell.run_line(line, &mut panel).unwrap();
+      }
+      write!(panel, "\r\x1B[K>{}", editor.line()).unwrap();
     }
-    */
-    last_k = k;
 
     if (VBLANK_COUNTER.read() % 64) == 1 {
       //x_off = x_off.wrapping_sub(1);
     }
 
-    y_off = panel.scroll_point();
-
     // wait for v_blank to begin
     VBlankIntrWait();
 
     // Update the display
-    BG0_X.write(x_off);
-    BG0_Y.write(y_off);
-    TextScreenblock::_8
-      .as_volblock()
-      .iter()
-      .zip(
-        cast_slice::<u32, u8>(&panel.chars)
-          .iter()
-          .copied()
-          .zip(cast_slice::<u32, u8>(&panel.banks).iter().copied()),
-      )
-      .for_each(|(va, (ch, p))| {
-        va.write(TextScreenEntry::from_id_bank(ch as u16, p as u16))
-      });
+    bg0.scroll_to(x_off, y_off);
+    panel.flush();
+    keyboard.draw();
+    dialog.draw();
   }
 }
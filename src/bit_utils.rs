@@ -40,6 +40,8 @@ pub const fn u16_get_region<const L: u32, const H: u32>(x: u16) -> u16 {
 
 /// Replaces a `L` to `H` (inclusive) bit region of the value.
 ///
+/// Any bits of `val` outside the region are ignored.
+///
 /// ## Panics
 /// * If `L` or `H` are out of range.
 /// * If `L` >= `H`
@@ -53,7 +55,7 @@ pub const fn u16_with_region<const L: u32, const H: u32>(
   assert!(L < H);
   let mask = (((1_u64 << (H - L + 1)) - 1) << L) as u16;
   assert!(mask.count_ones() == (H - L + 1));
-  (x & !mask) | val
+  (x & !mask) | (val & mask)
 }
 
 /// Like [`u16_get_region`] but the output is shifted down appropriately.
@@ -77,3 +79,49 @@ pub const fn u16_with_value<const L: u32, const H: u32>(
 ) -> u16 {
   u16_with_region::<L, H>(x, val << L)
 }
+
+#[cfg(all(test, not(target_arch = "arm")))]
+mod tests {
+  use super::*;
+  use quickcheck::quickcheck;
+
+  macro_rules! region_round_trips {
+    ($($name:ident: $l:literal - $h:literal),+ $(,)?) => {
+      quickcheck! {
+        $(
+          fn $name(x: u16, val: u16) -> bool {
+            let mask = (((1_u32 << ($h - $l + 1)) - 1) << $l) as u16;
+            let y = u16_with_value::<$l, $h>(x, val);
+            u16_get_value::<$l, $h>(y) == val & (mask >> $l)
+              && y & !mask == x & !mask
+              && u16_get_region::<$l, $h>(y) == (val << $l) & mask
+              && u16_with_region::<$l, $h>(y, u16_get_region::<$l, $h>(x)) == x
+          }
+        )+
+      }
+    };
+  }
+  region_round_trips! {
+    region_0_2: 0 - 2,
+    region_4_7: 4 - 7,
+    region_8_14: 8 - 14,
+    region_10_15: 10 - 15,
+    region_0_15: 0 - 15,
+  }
+
+  quickcheck! {
+    fn bit_round_trips(x: u16, val: bool) -> bool {
+      let y = u16_with_bit::<13>(x, val);
+      u16_get_bit::<13>(y) == val
+        && y & !(1 << 13) == x & !(1 << 13)
+        && u16_with_bit::<13>(y, u16_get_bit::<13>(x)) == x
+    }
+
+    fn low_and_high_bits_round_trip(x: u16, low: bool, high: bool) -> bool {
+      let y = u16_with_bit::<15>(u16_with_bit::<0>(x, low), high);
+      u16_get_bit::<0>(y) == low
+        && u16_get_bit::<15>(y) == high
+        && y & 0x7FFE == x & 0x7FFE
+    }
+  }
+}
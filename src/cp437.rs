@@ -0,0 +1,279 @@
+//! Translating between Unicode and [code page 437][cp437].
+//!
+//! CP437 is the character set of the default font, and the font's glyph
+//! index is simply the CP437 byte value.
+//!
+//! [cp437]: https://en.wikipedia.org/wiki/Code_page_437
+
+/// The Unicode character for each CP437 byte.
+///
+/// Bytes `0x01` to `0x1F` and `0x7F` are the glyphs that the font shows for
+/// those values, rather than control codes, and byte `0x00` is a blank.
+#[rustfmt::skip]
+pub const CP437_TO_CHAR: [char; 256] = [
+  '\0', '☺', '☻', '♥', '♦', '♣', '♠', '•',
+  '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼',
+  '►', '◄', '↕', '‼', '¶', '§', '▬', '↨',
+  '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+  ' ', '!', '"', '#', '$', '%', '&', '\'',
+  '(', ')', '*', '+', ',', '-', '.', '/',
+  '0', '1', '2', '3', '4', '5', '6', '7',
+  '8', '9', ':', ';', '<', '=', '>', '?',
+  '@', 'A', 'B', 'C', 'D', 'E', 'F', 'G',
+  'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O',
+  'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W',
+  'X', 'Y', 'Z', '[', '\\', ']', '^', '_',
+  '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g',
+  'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o',
+  'p', 'q', 'r', 's', 't', 'u', 'v', 'w',
+  'x', 'y', 'z', '{', '|', '}', '~', '⌂',
+  'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç',
+  'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+  'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù',
+  'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+  'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º',
+  '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+  '░', '▒', '▓', '│', '┤', '╡', '╢', '╖',
+  '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+  '└', '┴', '┬', '├', '─', '┼', '╞', '╟',
+  '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+  '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫',
+  '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+  'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ',
+  'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+  '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈',
+  '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+];
+
+/// Gets the Unicode character for a CP437 byte.
+#[inline]
+#[must_use]
+pub const fn cp437_to_char(byte: u8) -> char {
+  CP437_TO_CHAR[byte as usize]
+}
+
+/// Gets the CP437 byte for a Unicode character, if there is one.
+///
+/// ASCII control codes (other than `'\0'`) have no CP437 byte, since those
+/// byte values are used for glyphs such as `'☺'` and `'♥'`. A few characters
+/// that look the same as a CP437 glyph (such as Greek `'β'` and German `'ß'`)
+/// are also accepted.
+#[inline]
+#[must_use]
+pub const fn char_to_cp437(c: char) -> Option<u8> {
+  Some(match c {
+    '\0' => 0x00,
+    ' '..='~' => c as u8,
+    '☺' => 0x01,
+    '☻' => 0x02,
+    '♥' => 0x03,
+    '♦' => 0x04,
+    '♣' => 0x05,
+    '♠' => 0x06,
+    '•' => 0x07,
+    '◘' => 0x08,
+    '○' => 0x09,
+    '◙' => 0x0A,
+    '♂' => 0x0B,
+    '♀' => 0x0C,
+    '♪' => 0x0D,
+    '♫' => 0x0E,
+    '☼' => 0x0F,
+    '►' => 0x10,
+    '◄' => 0x11,
+    '↕' => 0x12,
+    '‼' => 0x13,
+    '¶' => 0x14,
+    '§' => 0x15,
+    '▬' => 0x16,
+    '↨' => 0x17,
+    '↑' => 0x18,
+    '↓' => 0x19,
+    '→' => 0x1A,
+    '←' => 0x1B,
+    '∟' => 0x1C,
+    '↔' => 0x1D,
+    '▲' => 0x1E,
+    '▼' => 0x1F,
+    '⌂' => 0x7F,
+    'Ç' => 0x80,
+    'ü' => 0x81,
+    'é' => 0x82,
+    'â' => 0x83,
+    'ä' => 0x84,
+    'à' => 0x85,
+    'å' => 0x86,
+    'ç' => 0x87,
+    'ê' => 0x88,
+    'ë' => 0x89,
+    'è' => 0x8A,
+    'ï' => 0x8B,
+    'î' => 0x8C,
+    'ì' => 0x8D,
+    'Ä' => 0x8E,
+    'Å' => 0x8F,
+    'É' => 0x90,
+    'æ' => 0x91,
+    'Æ' => 0x92,
+    'ô' => 0x93,
+    'ö' => 0x94,
+    'ò' => 0x95,
+    'û' => 0x96,
+    'ù' => 0x97,
+    'ÿ' => 0x98,
+    'Ö' => 0x99,
+    'Ü' => 0x9A,
+    '¢' => 0x9B,
+    '£' => 0x9C,
+    '¥' => 0x9D,
+    '₧' => 0x9E,
+    'ƒ' => 0x9F,
+    'á' => 0xA0,
+    'í' => 0xA1,
+    'ó' => 0xA2,
+    'ú' => 0xA3,
+    'ñ' => 0xA4,
+    'Ñ' => 0xA5,
+    'ª' => 0xA6,
+    'º' => 0xA7,
+    '¿' => 0xA8,
+    '⌐' => 0xA9,
+    '¬' => 0xAA,
+    '½' => 0xAB,
+    '¼' => 0xAC,
+    '¡' => 0xAD,
+    '«' => 0xAE,
+    '»' => 0xAF,
+    '░' => 0xB0,
+    '▒' => 0xB1,
+    '▓' => 0xB2,
+    '│' => 0xB3,
+    '┤' => 0xB4,
+    '╡' => 0xB5,
+    '╢' => 0xB6,
+    '╖' => 0xB7,
+    '╕' => 0xB8,
+    '╣' => 0xB9,
+    '║' => 0xBA,
+    '╗' => 0xBB,
+    '╝' => 0xBC,
+    '╜' => 0xBD,
+    '╛' => 0xBE,
+    '┐' => 0xBF,
+    '└' => 0xC0,
+    '┴' => 0xC1,
+    '┬' => 0xC2,
+    '├' => 0xC3,
+    '─' => 0xC4,
+    '┼' => 0xC5,
+    '╞' => 0xC6,
+    '╟' => 0xC7,
+    '╚' => 0xC8,
+    '╔' => 0xC9,
+    '╩' => 0xCA,
+    '╦' => 0xCB,
+    '╠' => 0xCC,
+    '═' => 0xCD,
+    '╬' => 0xCE,
+    '╧' => 0xCF,
+    '╨' => 0xD0,
+    '╤' => 0xD1,
+    '╥' => 0xD2,
+    '╙' => 0xD3,
+    '╘' => 0xD4,
+    '╒' => 0xD5,
+    '╓' => 0xD6,
+    '╫' => 0xD7,
+    '╪' => 0xD8,
+    '┘' => 0xD9,
+    '┌' => 0xDA,
+    '█' => 0xDB,
+    '▄' => 0xDC,
+    '▌' => 0xDD,
+    '▐' => 0xDE,
+    '▀' => 0xDF,
+    'α' => 0xE0,
+    'ß' => 0xE1,
+    'Γ' => 0xE2,
+    'π' => 0xE3,
+    'Σ' => 0xE4,
+    'σ' => 0xE5,
+    'µ' => 0xE6,
+    'τ' => 0xE7,
+    'Φ' => 0xE8,
+    'Θ' => 0xE9,
+    'Ω' => 0xEA,
+    'δ' => 0xEB,
+    '∞' => 0xEC,
+    'φ' => 0xED,
+    'ε' => 0xEE,
+    '∩' => 0xEF,
+    '≡' => 0xF0,
+    '±' => 0xF1,
+    '≥' => 0xF2,
+    '≤' => 0xF3,
+    '⌠' => 0xF4,
+    '⌡' => 0xF5,
+    '÷' => 0xF6,
+    '≈' => 0xF7,
+    '°' => 0xF8,
+    '∙' => 0xF9,
+    '·' => 0xFA,
+    '√' => 0xFB,
+    'ⁿ' => 0xFC,
+    '²' => 0xFD,
+    '■' => 0xFE,
+    '\u{A0}' => 0xFF,
+    'β' => 0xE1,
+    'μ' => 0xE6,
+    '∈' => 0xEE,
+    '∑' => 0xE4,
+    '∅' => 0xED,
+    _ => return None,
+  })
+}
+
+#[cfg(all(test, not(target_arch = "arm")))]
+mod tests {
+  use super::*;
+  use quickcheck::quickcheck;
+
+  #[test]
+  fn every_byte_round_trips() {
+    for byte in 0..=255_u8 {
+      let c = cp437_to_char(byte);
+      assert_eq!(char_to_cp437(c), Some(byte), "{:#04X} ({:?})", byte, c);
+    }
+  }
+
+  #[test]
+  fn printable_ascii_is_unchanged() {
+    for c in ' '..='~' {
+      assert_eq!(char_to_cp437(c), Some(c as u8));
+      assert_eq!(cp437_to_char(c as u8), c);
+    }
+  }
+
+  #[test]
+  fn control_codes_have_no_byte() {
+    for c in ('\x01'..='\x1F').chain(['\x7F']) {
+      assert_eq!(char_to_cp437(c), None, "{:?}", c);
+    }
+  }
+
+  #[test]
+  fn look_alikes_use_the_same_glyph() {
+    assert_eq!(char_to_cp437('β'), char_to_cp437('ß'));
+    assert_eq!(char_to_cp437('∈'), char_to_cp437('ε'));
+    assert_eq!(char_to_cp437('∑'), char_to_cp437('Σ'));
+  }
+
+  quickcheck! {
+    fn mapped_chars_agree_with_the_table(c: char) -> bool {
+      match char_to_cp437(c) {
+        Some(byte) => char_to_cp437(cp437_to_char(byte)) == Some(byte),
+        None => !CP437_TO_CHAR.contains(&c),
+      }
+    }
+  }
+}
@@ -0,0 +1,509 @@
+//! An RPG style dialog box that types its text out.
+
+use crate::{
+  char_to_cp437,
+  gba::{Keys, TextScreenEntry, TextScreenblock},
+};
+
+const TOP_LEFT: u8 = 0xC9;
+const TOP_RIGHT: u8 = 0xBB;
+const BOTTOM_LEFT: u8 = 0xC8;
+const BOTTOM_RIGHT: u8 = 0xBC;
+const HORIZONTAL: u8 = 0xCD;
+const VERTICAL: u8 = 0xBA;
+const MORE_ARROW: u8 = 0x1F;
+const CHOICE_ARROW: u8 = 0x10;
+
+/// Something that happened in a [`DialogBox`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogEvent {
+  /// All the text was shown and the player pressed A.
+  Finished,
+  /// The player picked one of the choices.
+  Chosen(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DialogState {
+  Idle,
+  Typing,
+  WaitingForPage,
+  WaitingForEnd,
+  Choosing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tag {
+  Color(Option<u8>),
+  Pause(u8),
+  Page,
+  Unknown,
+}
+
+/// Parses a `{...}` tag at the start of `text`, giving the tag and its
+/// length in bytes.
+#[must_use]
+fn parse_tag(text: &str) -> Option<(Tag, usize)> {
+  let end = text.strip_prefix('{')?.find('}')?;
+  let body = &text[1..1 + end];
+  let number = |s: &str| s.parse::<u8>().ok();
+  let tag = if body == "c" {
+    Tag::Color(None)
+  } else if body == "page" {
+    Tag::Page
+  } else if let Some(n) = body.strip_prefix('c').and_then(number) {
+    Tag::Color(Some(n & 0xF))
+  } else if let Some(n) = body.strip_prefix('p').and_then(number) {
+    Tag::Pause(n)
+  } else {
+    Tag::Unknown
+  };
+  Some((tag, end + 2))
+}
+
+/// The number of glyphs in the word at the start of `text`, not counting
+/// tags.
+#[must_use]
+fn word_len(text: &str) -> usize {
+  let mut count = 0;
+  let mut rest = text;
+  while let Some(c) = rest.chars().next() {
+    if c == ' ' || c == '\n' {
+      break;
+    }
+    match parse_tag(rest) {
+      Some((Tag::Page, _)) => break,
+      Some((_, len)) => rest = &rest[len..],
+      None => {
+        count += 1;
+        rest = &rest[c.len_utf8()..];
+      }
+    }
+  }
+  count
+}
+
+/// A framed box that reveals text a character at a time.
+///
+/// The box is drawn into a screenblock, so it's best to give it a background
+/// of its own. The text area is `W` by `H` tiles, with a one tile frame
+/// around it. The frame uses the CP437 double line characters, so the font
+/// should be the one from [`decompress_cp437_data_to`].
+///
+/// Call [`update`](Self::update) once per frame with the newly pressed keys,
+/// and [`draw`](Self::draw) during VBlank.
+///
+/// Text is word wrapped, and when the box is full it waits for A before
+/// showing the next page. Pressing A or B while text is being typed shows
+/// the rest of the page at once. The text can have tags in it:
+/// * `{c3}` changes the palbank of the following text to 3, and `{c}` goes back
+///   to the box's palbank.
+/// * `{p30}` pauses for 30 frames before the next character.
+/// * `{page}` starts a new page.
+///
+/// Anything else in braces is skipped.
+///
+/// [`decompress_cp437_data_to`]: crate::gba::decompress_cp437_data_to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DialogBox<const W: usize, const H: usize> {
+  screenblock: TextScreenblock,
+  origin_x: usize,
+  origin_y: usize,
+  font_offset: u16,
+  palbank: u8,
+  frames_per_char: u8,
+  /// 0 means an empty cell.
+  chars: [[u8; W]; H],
+  banks: [[u8; W]; H],
+  /// Frames to wait before each cell is shown.
+  pauses: [[u8; W]; H],
+  revealed: usize,
+  timer: u8,
+  text: &'static str,
+  next_page: usize,
+  color: Option<u8>,
+  speaker: Option<&'static str>,
+  choices: &'static [&'static str],
+  choice_row: usize,
+  selected: usize,
+  state: DialogState,
+  visible: bool,
+  dirty: bool,
+}
+impl<const W: usize, const H: usize> DialogBox<W, H> {
+  /// Makes a hidden dialog box in the top left of the screenblock.
+  ///
+  /// ## Panics
+  /// * If `W` or `H` are 0 or more than 30.
+  #[inline]
+  #[must_use]
+  pub const fn new(screenblock: TextScreenblock) -> Self {
+    assert!(W > 0 && W <= 30, "dialog width must be 1 to 30");
+    assert!(H > 0 && H <= 30, "dialog height must be 1 to 30");
+    Self {
+      screenblock,
+      origin_x: 0,
+      origin_y: 0,
+      font_offset: 0,
+      palbank: 0,
+      frames_per_char: 2,
+      chars: [[0; W]; H],
+      banks: [[0; W]; H],
+      pauses: [[0; W]; H],
+      revealed: 0,
+      timer: 0,
+      text: "",
+      next_page: 0,
+      color: None,
+      speaker: None,
+      choices: &[],
+      choice_row: 0,
+      selected: 0,
+      state: DialogState::Idle,
+      visible: false,
+      dirty: false,
+    }
+  }
+  /// Moves where the frame's top left corner is in the screenblock.
+  ///
+  /// ## Panics
+  /// * If the box (with its frame) wouldn't fit in the screenblock.
+  #[inline]
+  #[must_use]
+  pub const fn with_origin(self, x: usize, y: usize) -> Self {
+    assert!(
+      x + W + 2 <= 32 && y + H + 2 <= 32,
+      "dialog box must fit in the screenblock"
+    );
+    Self { origin_x: x, origin_y: y, ..self }
+  }
+  /// Sets the tile index of the font's first glyph.
+  #[inline]
+  #[must_use]
+  pub const fn with_font_offset(self, font_offset: u16) -> Self {
+    Self { font_offset, ..self }
+  }
+  /// Sets the palbank of the frame and of text without a color tag.
+  #[inline]
+  #[must_use]
+  pub const fn with_palbank(self, palbank: u8) -> Self {
+    Self { palbank: palbank & 0xF, ..self }
+  }
+  /// Sets how many frames each character takes to appear.
+  #[inline]
+  #[must_use]
+  pub const fn with_speed(self, frames_per_char: u8) -> Self {
+    Self { frames_per_char, ..self }
+  }
+
+  /// If the box is showing text or waiting for the player.
+  #[inline]
+  #[must_use]
+  pub const fn is_active(&self) -> bool {
+    !matches!(self.state, DialogState::Idle)
+  }
+  /// If the box is drawn at all.
+  #[inline]
+  #[must_use]
+  pub const fn is_visible(&self) -> bool {
+    self.visible
+  }
+
+  /// Starts showing some text, with no speaker and no choices.
+  pub fn start(&mut self, text: &'static str) {
+    self.text = text;
+    self.next_page = 0;
+    self.color = None;
+    self.speaker = None;
+    self.choices = &[];
+    self.selected = 0;
+    self.visible = true;
+    self.layout_page();
+  }
+  /// Sets the name shown on the top of the frame.
+  #[inline]
+  pub fn set_speaker(&mut self, speaker: Option<&'static str>) {
+    self.speaker = speaker;
+    self.dirty = true;
+  }
+  /// Sets the choices offered once the text is finished.
+  ///
+  /// The choice the player picks is given back as a
+  /// [`DialogEvent::Chosen`]. With no choices, the box gives a
+  /// [`DialogEvent::Finished`] instead.
+  #[inline]
+  pub fn set_choices(&mut self, choices: &'static [&'static str]) {
+    self.choices = choices;
+    self.selected = 0;
+  }
+  /// Stops and hides the box.
+  #[inline]
+  pub fn close(&mut self) {
+    self.state = DialogState::Idle;
+    self.visible = false;
+    self.dirty = true;
+  }
+
+  /// Advances the box by one frame.
+  pub fn update(&mut self, pressed: Keys) -> Option<DialogEvent> {
+    match self.state {
+      DialogState::Idle => None,
+      DialogState::Typing => {
+        if pressed.a() || pressed.b() {
+          self.revealed = W * H;
+          self.dirty = true;
+        } else if self.timer > 0 {
+          self.timer -= 1;
+        } else if let Some(i) = self.next_glyph(self.revealed) {
+          self.revealed = i + 1;
+          self.timer = self.delay_before(self.revealed);
+          self.dirty = true;
+        }
+        if self.next_glyph(self.revealed).is_none() {
+          self.finish_page();
+        }
+        None
+      }
+      DialogState::WaitingForPage => {
+        if pressed.a() {
+          self.layout_page();
+        }
+        None
+      }
+      DialogState::WaitingForEnd => {
+        if pressed.a() {
+          self.state = DialogState::Idle;
+          self.dirty = true;
+          Some(DialogEvent::Finished)
+        } else {
+          None
+        }
+      }
+      DialogState::Choosing => {
+        let count = self.choices.len();
+        if pressed.up() {
+          self.selected = (self.selected + count - 1) % count;
+          self.dirty = true;
+        }
+        if pressed.down() {
+          self.selected = (self.selected + 1) % count;
+          self.dirty = true;
+        }
+        if pressed.a() {
+          self.state = DialogState::Idle;
+          self.dirty = true;
+          Some(DialogEvent::Chosen(self.selected))
+        } else {
+          None
+        }
+      }
+    }
+  }
+
+  /// Draws the box, if it's changed since it was last drawn.
+  ///
+  /// Call this during VBlank.
+  pub fn draw(&mut self) {
+    if !self.dirty {
+      return;
+    }
+    self.dirty = false;
+    let block = self.screenblock.as_volblock();
+    let put = |x: usize, y: usize, byte: u8, palbank: u8| {
+      let id = self.font_offset.wrapping_add(byte as u16);
+      block
+        .index((self.origin_y + y) * 32 + self.origin_x + x)
+        .write(TextScreenEntry::from_id_bank(id, palbank as u16));
+    };
+    if !self.visible {
+      for y in 0..(H + 2) {
+        for x in 0..(W + 2) {
+          put(x, y, b' ', self.palbank);
+        }
+      }
+      return;
+    }
+    // frame
+    let mut top = [HORIZONTAL; W];
+    if let Some(name) = self.speaker {
+      let bytes = (*b" ")
+        .into_iter()
+        .chain(name.chars().map(|c| char_to_cp437(c).unwrap_or(b'?')))
+        .chain(*b" ");
+      for (i, byte) in bytes.enumerate() {
+        let x = i + 1;
+        if x >= W {
+          break;
+        }
+        top[x] = byte;
+      }
+    }
+    put(0, 0, TOP_LEFT, self.palbank);
+    for (x, byte) in top.iter().copied().enumerate() {
+      put(x + 1, 0, byte, self.palbank);
+    }
+    put(W + 1, 0, TOP_RIGHT, self.palbank);
+    for y in 1..=H {
+      put(0, y, VERTICAL, self.palbank);
+      put(W + 1, y, VERTICAL, self.palbank);
+    }
+    put(0, H + 1, BOTTOM_LEFT, self.palbank);
+    for x in 1..=W {
+      put(x, H + 1, HORIZONTAL, self.palbank);
+    }
+    if matches!(
+      self.state,
+      DialogState::WaitingForPage | DialogState::WaitingForEnd
+    ) {
+      put(W, H + 1, MORE_ARROW, self.palbank);
+    }
+    put(W + 1, H + 1, BOTTOM_RIGHT, self.palbank);
+    // text
+    for y in 0..H {
+      for x in 0..W {
+        let (byte, palbank) = if y * W + x < self.revealed {
+          match self.chars[y][x] {
+            0 => (b' ', self.palbank),
+            byte => (byte, self.banks[y][x]),
+          }
+        } else {
+          (b' ', self.palbank)
+        };
+        put(x + 1, y + 1, byte, palbank);
+      }
+    }
+    // choices
+    if self.state == DialogState::Choosing {
+      for (i, choice) in self.choices.iter().enumerate() {
+        let y = self.choice_row + i;
+        if y >= H {
+          break;
+        }
+        let arrow = if i == self.selected { CHOICE_ARROW } else { b' ' };
+        put(1, y + 1, arrow, self.palbank);
+        for (x, c) in choice.chars().take(W.saturating_sub(2)).enumerate() {
+          put(x + 3, y + 1, char_to_cp437(c).unwrap_or(b'?'), self.palbank);
+        }
+      }
+    }
+  }
+
+  /// The index of the first non-empty cell at or after `i`.
+  #[inline]
+  #[must_use]
+  fn next_glyph(&self, i: usize) -> Option<usize> {
+    (i..(W * H)).find(|&i| self.chars[i / W][i % W] != 0)
+  }
+
+  /// How long to wait before showing the next glyph after `i`.
+  #[inline]
+  #[must_use]
+  fn delay_before(&self, i: usize) -> u8 {
+    let pause = match self.next_glyph(i) {
+      Some(j) => self.pauses[j / W][j % W],
+      None => 0,
+    };
+    self.frames_per_char.saturating_sub(1).saturating_add(pause)
+  }
+
+  /// Called once a page has been fully revealed.
+  fn finish_page(&mut self) {
+    self.dirty = true;
+    if self.next_page < self.text.len() {
+      self.state = DialogState::WaitingForPage;
+    } else if self.choices.is_empty() {
+      self.state = DialogState::WaitingForEnd;
+    } else {
+      // put the choices under the text if they fit, otherwise on their own
+      let used_rows = (0..H)
+        .rev()
+        .find(|&y| self.chars[y].iter().any(|&c| c != 0))
+        .map_or(0, |y| y + 1);
+      if used_rows + self.choices.len() > H {
+        self.chars = [[0; W]; H];
+        self.choice_row = 0;
+      } else {
+        self.choice_row = used_rows;
+      }
+      self.state = DialogState::Choosing;
+    }
+  }
+
+  /// Lays out the next page of text into the cells.
+  fn layout_page(&mut self) {
+    self.chars = [[0; W]; H];
+    self.banks = [[0; W]; H];
+    self.pauses = [[0; W]; H];
+    self.revealed = 0;
+    self.state = DialogState::Typing;
+    self.dirty = true;
+    let text = self.text;
+    let mut pos = self.next_page;
+    let (mut x, mut y) = (0, 0);
+    let mut pause = 0_u8;
+    let mut at_word_start = true;
+    while y < H {
+      let rest = &text[pos..];
+      let c = match rest.chars().next() {
+        Some(c) => c,
+        None => break,
+      };
+      if let Some((tag, len)) = parse_tag(rest) {
+        match tag {
+          Tag::Page => {
+            pos += len;
+            break;
+          }
+          Tag::Color(color) => self.color = color,
+          Tag::Pause(frames) => pause = pause.saturating_add(frames),
+          Tag::Unknown => (),
+        }
+        pos += len;
+        continue;
+      }
+      if c == '\n' {
+        pos += 1;
+        x = 0;
+        y += 1;
+        at_word_start = true;
+        continue;
+      }
+      if c == ' ' {
+        pos += 1;
+        at_word_start = true;
+        // spaces at the start of a wrapped line are dropped
+        if x == 0 || x >= W {
+          continue;
+        }
+      } else if at_word_start {
+        at_word_start = false;
+        let len = word_len(rest);
+        if x > 0 && x + len > W {
+          x = 0;
+          y += 1;
+          if y >= H {
+            break;
+          }
+        }
+      }
+      if x >= W {
+        // a word longer than a whole line
+        x = 0;
+        y += 1;
+        if y >= H {
+          break;
+        }
+      }
+      if c != ' ' {
+        pos += c.len_utf8();
+      }
+      self.chars[y][x] = char_to_cp437(c).unwrap_or(b'?');
+      self.banks[y][x] = self.color.unwrap_or(self.palbank);
+      self.pauses[y][x] = pause;
+      pause = 0;
+      x += 1;
+    }
+    self.next_page = pos;
+    self.timer = self.delay_before(0);
+  }
+}
@@ -58,6 +58,21 @@ macro_rules! impl_common_fixed_point_stuff {
         pub const fn new(n: $t) -> Self {
           Self(n << BITS)
         }
+        /// Wraps a raw value, which already has `BITS` of fraction.
+        #[inline] #[must_use]
+        pub const fn from_bits(n: $t) -> Self {
+          Self(n)
+        }
+        /// Unwraps the raw value, which has `BITS` of fraction.
+        #[inline] #[must_use]
+        pub const fn to_bits(self) -> $t {
+          self.0
+        }
+        /// The integer part, rounded towards negative infinity.
+        #[inline] #[must_use]
+        pub const fn to_int(self) -> $t {
+          self.0 >> BITS
+        }
         #[inline] #[must_use]
         pub const fn as_fx_i8(self) -> Fx<i8, BITS> {
           Fx(self.0 as i8)
@@ -163,3 +178,103 @@ macro_rules! impl_signed_fixed_point_stuff {
 impl_signed_fixed_point_stuff! {
   i8, i16, i32
 }
+
+#[cfg(all(test, not(target_arch = "arm")))]
+mod tests {
+  use super::*;
+  use quickcheck::quickcheck;
+
+  type F = Fx<i32, 8>;
+
+  quickcheck! {
+    fn bits_round_trip(n: i32, u: u16) -> bool {
+      F::from_bits(n).to_bits() == n
+        && Fx::<u16, 4>::from_bits(u).to_bits() == u
+    }
+
+    fn int_round_trips(n: i16) -> bool {
+      F::new(n as i32).to_int() == n as i32
+        && F::new(n as i32).to_bits() == (n as i32) * 256
+    }
+
+    fn to_int_rounds_down(n: i32) -> bool {
+      let int = F::from_bits(n).to_int();
+      int == n.div_euclid(256) && F::new(int).to_bits() <= n
+    }
+
+    fn add_and_sub_match_the_raw_values(a: i16, b: i16) -> bool {
+      let (x, y) = (F::from_bits(a as i32), F::from_bits(b as i32));
+      (x + y).to_bits() == a as i32 + b as i32
+        && (x - y).to_bits() == a as i32 - b as i32
+        && x + y == y + x
+        && x + y - y == x
+    }
+
+    fn assign_ops_match(a: i16, b: i16) -> bool {
+      let (x, y) = (F::from_bits(a as i32), F::from_bits(b as i32));
+      let mut sum = x;
+      sum += y;
+      let mut difference = x;
+      difference -= y;
+      let mut product = x;
+      product *= y;
+      sum == x + y && difference == x - y && product == x * y
+    }
+
+    fn mul_matches_int_mul(a: i8, b: i8) -> bool {
+      F::new(a as i32) * F::new(b as i32) == F::new(a as i32 * b as i32)
+    }
+
+    fn mul_by_one_is_identity(a: i16) -> bool {
+      let x = F::from_bits(a as i32);
+      x * F::new(1) == x && F::new(1) * x == x
+    }
+
+    fn mul_rounds_down(a: i16, b: i16) -> bool {
+      let (x, y) = (F::from_bits(a as i32), F::from_bits(b as i32));
+      (x * y).to_bits() == (a as i32 * b as i32).div_euclid(256)
+        && x * y == y * x
+    }
+
+    fn overflow_ops_agree(a: i16, b: i16) -> bool {
+      type S = Fx<i16, 8>;
+      let (x, y) = (S::from_bits(a), S::from_bits(b));
+      let (wrapped, overflowed) = x.overflowing_add(y);
+      let saturated = if b < 0 { i16::MIN } else { i16::MAX };
+      wrapped == x.wrapping_add(y)
+        && x.checked_add(y) == if overflowed { None } else { Some(wrapped) }
+        && x.saturating_add(y)
+          == x.checked_add(y).unwrap_or(S::from_bits(saturated))
+    }
+
+    fn sub_overflow_ops_agree(a: u16, b: u16) -> bool {
+      type U = Fx<u16, 4>;
+      let (x, y) = (U::from_bits(a), U::from_bits(b));
+      let (wrapped, overflowed) = x.overflowing_sub(y);
+      wrapped == x.wrapping_sub(y)
+        && overflowed == (b > a)
+        && x.checked_sub(y) == if overflowed { None } else { Some(wrapped) }
+        && x.saturating_sub(y) == x.checked_sub(y).unwrap_or(U::from_bits(0))
+    }
+
+    fn neg_abs_and_signum_agree(a: i32) -> bool {
+      let x = F::from_bits(a);
+      let zero = F::from_bits(0);
+      if a == i32::MIN {
+        return x.wrapping_abs() == x;
+      }
+      -(-x) == x
+        && x.abs() == if x < zero { -x } else { x }
+        && x.wrapping_abs() == x.abs()
+        && x.signum() == F::new(a.signum())
+    }
+
+    fn conversions_keep_the_bits(a: i16) -> bool {
+      let x = Fx::<i16, 8>::from_bits(a);
+      x.as_fx_i32().to_bits() == a as i32
+        && x.as_fx_u16().to_bits() == a as u16
+        && x.as_fx_i32().as_fx_i16() == x
+        && x.as_fx_i8().to_bits() == a as i8
+    }
+  }
+}
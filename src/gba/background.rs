@@ -0,0 +1,179 @@
+use super::*;
+
+const BG_CONTROL: [VolAddress<BgControl, Safe, Safe>; 4] =
+  [BG0CNT, BG1CNT, BG2CNT, BG3CNT];
+const BG_X: [VolAddress<u16, (), Safe>; 4] = [BG0_X, BG1_X, BG2_X, BG3_X];
+const BG_Y: [VolAddress<u16, (), Safe>; 4] = [BG0_Y, BG1_Y, BG2_Y, BG3_Y];
+
+/// A handle to one of the four backgrounds, used as a text mode layer.
+///
+/// The handle holds the [`BgControl`] value for the layer, and knows which
+/// scroll registers and screenblocks go with it. Nothing is written to the
+/// hardware until you call one of the methods that says it does so.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Background {
+  index: usize,
+  control: BgControl,
+}
+impl Background {
+  /// Makes a new text background handle.
+  ///
+  /// The layer's tiles are `tile_count` tiles starting at `charblock`, which
+  /// are 4bpp unless `is_8bpp` is set. The map uses
+  /// `size.screenblock_count()` screenblocks in a row starting at
+  /// `screenblock`. The color depth can't be changed later, since that
+  /// changes how much VRAM the tiles use.
+  ///
+  /// ## Failure
+  /// * If `index` is 4 or more.
+  /// * If there are more than 1024 tiles, or they would run past the end of
+  ///   background VRAM.
+  /// * If the map would run past the last screenblock.
+  /// * If the map overlaps the tiles.
+  ///
+  /// This only checks the layer against itself. To check several layers
+  /// against each other, use a [`VramPlan`].
+  #[inline]
+  #[must_use]
+  pub const fn try_new(
+    index: usize, charblock: BgCharblock, tile_count: usize, is_8bpp: bool,
+    screenblock: TextScreenblock, size: TextScreenSize,
+  ) -> Option<Self> {
+    if index >= 4 || tile_count > 1024 {
+      return None;
+    }
+    let sb_start = screenblock.index();
+    let sb_end = sb_start + size.screenblock_count();
+    if sb_end > 32 {
+      return None;
+    }
+    let tile_bytes = if is_8bpp { 64 } else { 32 };
+    let tiles_start = charblock.index() * 8;
+    let tiles_end = tiles_start + (tile_count * tile_bytes).div_ceil(2 * 1024);
+    if tiles_end > 32 {
+      return None;
+    }
+    if sb_start < tiles_end && tiles_start < sb_end {
+      return None;
+    }
+    let control = BgControl::new()
+      .with_charblock(charblock.index() as u16)
+      .with_8bpp(is_8bpp)
+      .with_screenblock(sb_start as u16)
+      .with_text_screen_size(size);
+    Some(Self { index, control })
+  }
+
+  /// Makes a handle without any checks, for use by [`VramPlan`].
+  #[inline]
+  #[must_use]
+  pub(crate) const fn from_parts(index: usize, control: BgControl) -> Self {
+    Self { index, control }
+  }
+
+  /// As [`try_new`](Self::try_new), but panics on failure.
+  #[inline]
+  #[must_use]
+  pub const fn new(
+    index: usize, charblock: BgCharblock, tile_count: usize, is_8bpp: bool,
+    screenblock: TextScreenblock, size: TextScreenSize,
+  ) -> Self {
+    match Self::try_new(
+      index,
+      charblock,
+      tile_count,
+      is_8bpp,
+      screenblock,
+      size,
+    ) {
+      Some(bg) => bg,
+      None => panic!("illegal background configuration"),
+    }
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn with_z_index(self, z: u16) -> Self {
+    Self { index: self.index, control: self.control.with_z_index(z) }
+  }
+  #[inline]
+  #[must_use]
+  pub const fn with_mosaic(self, b: bool) -> Self {
+    Self { index: self.index, control: self.control.with_mosaic(b) }
+  }
+
+  /// Which background this is, `0..4`.
+  #[inline]
+  #[must_use]
+  pub const fn index(self) -> usize {
+    self.index
+  }
+  #[inline]
+  #[must_use]
+  pub const fn control(self) -> BgControl {
+    self.control
+  }
+  #[inline]
+  #[must_use]
+  pub const fn size(self) -> TextScreenSize {
+    self.control.text_screen_size()
+  }
+  #[inline]
+  #[must_use]
+  pub const fn charblock(self) -> BgCharblock {
+    BgCharblock(self.control.charblock() as usize)
+  }
+
+  /// Gets the `n`th screenblock of the map.
+  ///
+  /// ## Panics
+  /// * If `n` is not less than the screenblock count of the map's size.
+  #[inline]
+  #[must_use]
+  pub const fn screenblock(self, n: usize) -> TextScreenblock {
+    assert!(n < self.size().screenblock_count());
+    TextScreenblock::new(self.control.screenblock() as usize + n)
+  }
+
+  /// Writes the control value to this background's control register.
+  #[inline]
+  pub fn write_control(self) {
+    BG_CONTROL[self.index].write(self.control)
+  }
+
+  /// Sets the scroll registers of this background.
+  #[inline]
+  pub fn scroll_to(self, x: u16, y: u16) {
+    BG_X[self.index].write(x);
+    BG_Y[self.index].write(y);
+  }
+
+  /// Writes an entry to the map, in tile coordinates of the whole map.
+  ///
+  /// Coordinates wrap around the size of the map, the same as the display
+  /// does when scrolling.
+  #[inline]
+  pub fn set_tile(self, x: usize, y: usize, entry: TextScreenEntry) {
+    self.tile_address(x, y).write(entry)
+  }
+
+  /// Reads an entry from the map, in tile coordinates of the whole map.
+  #[inline]
+  #[must_use]
+  pub fn get_tile(self, x: usize, y: usize) -> TextScreenEntry {
+    self.tile_address(x, y).read()
+  }
+
+  #[inline]
+  #[must_use]
+  fn tile_address(
+    self, x: usize, y: usize,
+  ) -> VolAddress<TextScreenEntry, Safe, Safe> {
+    let size = self.size();
+    let x = x % size.tiles_wide();
+    let y = y % size.tiles_tall();
+    let blocks_wide = size.tiles_wide() / 32;
+    let n = (y / 32) * blocks_wide + (x / 32);
+    self.screenblock(n).as_volblock().index((y % 32) * 32 + (x % 32))
+  }
+}
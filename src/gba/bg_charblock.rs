@@ -2,7 +2,7 @@ use super::*;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[repr(transparent)]
-pub struct BgCharblock(usize);
+pub struct BgCharblock(pub(crate) usize);
 impl BgCharblock {
   pub const _0: Self = Self(0);
   pub const _1: Self = Self(1);
@@ -26,6 +26,13 @@ impl BgCharblock {
     }
   }
 
+  /// The index of this charblock, `0..4`.
+  #[inline]
+  #[must_use]
+  pub const fn index(self) -> usize {
+    self.0
+  }
+
   #[inline]
   #[must_use]
   pub const fn tiles4(self) -> VolRegion<Tile4, Safe, Safe> {
@@ -1,5 +1,83 @@
 #![allow(non_snake_case)]
 
+/// `swi #0x00`
+///
+/// Restarts the program from the start of ROM.
+///
+/// EWRAM and most of IWRAM are left as they are, but the BIOS clears the top
+/// 512 bytes of IWRAM and resets the IO registers it uses.
+#[inline]
+pub fn SoftReset() -> ! {
+  const SWI_NUM: usize = 0x00;
+  #[cfg(target_feature = "thumb-mode")]
+  const SWI_COMMENT: usize = SWI_NUM;
+  #[cfg(not(target_feature = "thumb-mode"))]
+  const SWI_COMMENT: usize = SWI_NUM << 16;
+  unsafe { asm!("swi #{n}", n = const SWI_COMMENT, options(noreturn)) };
+}
+
+/// `swi #0x02`
+///
+/// Stops the CPU until an interrupt that's enabled in `IE` happens. `IME`
+/// doesn't need to be on, but the interrupt is only handled if it is.
+#[inline]
+pub fn Halt() {
+  const SWI_NUM: usize = 0x02;
+  #[cfg(target_feature = "thumb-mode")]
+  const SWI_COMMENT: usize = SWI_NUM;
+  #[cfg(not(target_feature = "thumb-mode"))]
+  const SWI_COMMENT: usize = SWI_NUM << 16;
+  unsafe {
+    asm!(
+      "swi #{n}",
+      n = const SWI_COMMENT,
+      out("r0") _,
+      out("r1") _,
+      out("r3") _,
+      options(preserves_flags),
+    )
+  };
+}
+
+/// Stops the program for good, with the CPU in [`Halt`].
+///
+/// `IME` and `IE` are cleared first so that nothing wakes it up. This is how
+/// the error screens end.
+pub fn halt_forever() -> ! {
+  super::IME.write(false);
+  super::IE.write(super::IrqBits::new());
+  loop {
+    Halt();
+  }
+}
+
+/// `swi #0x03`
+///
+/// Stops the CPU, sound, video, and timers until a keypad, game pak, or
+/// serial interrupt happens.
+///
+/// The interrupt must be enabled in `IE` (and `IME` must be on) or this will
+/// never return. The display should be put in forced blank and sound turned
+/// off before calling this, see [`sleep_until_keys`](super::sleep_until_keys).
+#[inline]
+pub fn Stop() {
+  const SWI_NUM: usize = 0x03;
+  #[cfg(target_feature = "thumb-mode")]
+  const SWI_COMMENT: usize = SWI_NUM;
+  #[cfg(not(target_feature = "thumb-mode"))]
+  const SWI_COMMENT: usize = SWI_NUM << 16;
+  unsafe {
+    asm!(
+      "swi #{n}",
+      n = const SWI_COMMENT,
+      out("r0") _,
+      out("r1") _,
+      out("r3") _,
+      options(preserves_flags),
+    )
+  };
+}
+
 /// `swi #0x05`
 ///
 /// Works as per `IntrWait`, but always discards old flags, and then waits for
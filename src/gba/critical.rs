@@ -0,0 +1,124 @@
+use core::marker::PhantomData;
+
+use super::*;
+
+/// Proof that interrupts are off.
+///
+/// You get one of these inside [`critical_section`], and it can't outlive the
+/// closure, so any function that takes one knows that no irq handler can run
+/// while it's working.
+#[derive(Debug, Clone, Copy)]
+pub struct CriticalSection<'cs>(PhantomData<&'cs ()>);
+
+/// Runs `f` with interrupts off.
+///
+/// `IME` is cleared for the duration of the call and then put back how it
+/// was, so critical sections can nest, and they're fine to use in an irq
+/// handler too.
+#[inline]
+pub fn critical_section<R>(f: impl FnOnce(CriticalSection<'_>) -> R) -> R {
+  let ime = IME.read();
+  IME.write(false);
+  let r = f(CriticalSection(PhantomData));
+  IME.write(ime);
+  r
+}
+
+/// A value that can be shared between the main program and irq handlers.
+///
+/// [`GbaCell`] only works for values that are read and written with a single
+/// instruction. A `GbaMutex` can hold anything, because it's only accessed
+/// inside a [`critical_section`], where no irq handler can interrupt.
+///
+/// ```ignore
+/// static SCORE: GbaMutex<Score> = GbaMutex::new(Score::new());
+///
+/// fn on_vblank() {
+///   SCORE.lock(|score| score.tick());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct GbaMutex<T> {
+  locked: GbaCell<bool>,
+  value: UnsafeCell<T>,
+}
+// Safety: the value is only ever accessed inside a critical section, and the
+// lock flag stops that access from being re-entered.
+unsafe impl<T: Send> Sync for GbaMutex<T> {}
+impl<T> GbaMutex<T> {
+  /// Makes a new mutex.
+  #[inline]
+  #[must_use]
+  pub const fn new(value: T) -> Self {
+    Self {
+      // Safety: `bool` is accessed with a single instruction.
+      locked: unsafe { GbaCell::new(false) },
+      value: UnsafeCell::new(value),
+    }
+  }
+
+  /// Runs `f` on the value, with interrupts off.
+  ///
+  /// ## Panics
+  /// * If the mutex is already locked, which can only happen if `f` tries to
+  ///   lock the same mutex again.
+  #[inline]
+  pub fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+    match self.try_lock(f) {
+      Some(r) => r,
+      None => panic!("GbaMutex locked twice"),
+    }
+  }
+
+  /// As [`lock`](Self::lock), but gives `None` instead of panicking if the
+  /// mutex is already locked.
+  pub fn try_lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+    critical_section(|cs| self.try_lock_in(cs, f))
+  }
+
+  /// As [`try_lock`](Self::try_lock), but inside a critical section that's
+  /// already going, such as when locking more than one mutex at once.
+  pub fn try_lock_in<R>(
+    &self, _cs: CriticalSection<'_>, f: impl FnOnce(&mut T) -> R,
+  ) -> Option<R> {
+    if self.locked.read() {
+      return None;
+    }
+    self.locked.write(true);
+    // Safety: interrupts are off and the lock flag was clear, so nothing else
+    // has a reference to the value.
+    let r = f(unsafe { &mut *self.value.get() });
+    self.locked.write(false);
+    Some(r)
+  }
+
+  /// Gets the value, without needing to lock, when you've got the mutex
+  /// itself by `&mut`.
+  #[inline]
+  #[must_use]
+  pub fn get_mut(&mut self) -> &mut T {
+    self.value.get_mut()
+  }
+}
+
+/// Lets crates that use the `critical-section` crate (such as `heapless`) work
+/// on the GBA, with the same `IME` handling as [`critical_section`].
+#[cfg(feature = "critical-section")]
+mod critical_section_impl {
+  use super::IME;
+
+  struct GbaCriticalSection;
+  critical_section::set_impl!(GbaCriticalSection);
+
+  unsafe impl critical_section::Impl for GbaCriticalSection {
+    unsafe fn acquire() -> critical_section::RawRestoreState {
+      let ime = IME.read();
+      IME.write(false);
+      ime
+    }
+
+    unsafe fn release(ime: critical_section::RawRestoreState) {
+      IME.write(ime);
+    }
+  }
+}
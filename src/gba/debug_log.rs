@@ -0,0 +1,219 @@
+use core::fmt::Write;
+
+use super::*;
+
+/// How important a debug message is.
+///
+/// These are the same numbers that mGBA uses for its log levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u16)]
+pub enum DebugLevel {
+  Fatal = 0,
+  Error = 1,
+  Warn = 2,
+  Info = 3,
+  Debug = 4,
+}
+
+/// Where debug text goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum DebugOutput {
+  /// Nowhere, such as on real hardware.
+  None = 0,
+  /// mGBA's log window.
+  Mgba = 1,
+  /// no$gba's debug message window.
+  NoCash = 2,
+}
+impl DebugOutput {
+  /// Checks which emulator (if any) the program is running in.
+  ///
+  /// This also turns on mGBA's debug registers, if it finds them.
+  #[must_use]
+  pub fn detect() -> Self {
+    MGBA_ENABLE.write(0xC0DE);
+    if MGBA_ENABLE.read() == 0x1DEA {
+      Self::Mgba
+    } else if NOCASH_ID.iter().zip(b"no$gba").all(|(a, b)| a.read() == *b) {
+      Self::NoCash
+    } else {
+      Self::None
+    }
+  }
+}
+
+const MGBA_ENABLE: VolAddress<u16, Safe, Safe> =
+  unsafe { VolAddress::new(0x04FF_F780) };
+const MGBA_FLAGS: VolAddress<u16, Safe, Safe> =
+  unsafe { VolAddress::new(0x04FF_F700) };
+const MGBA_BUFFER: VolBlock<u8, Safe, Safe, 256> =
+  unsafe { VolBlock::new(0x04FF_F600) };
+const NOCASH_ID: VolBlock<u8, Safe, (), 6> =
+  unsafe { VolBlock::new(0x04FF_FA00) };
+const NOCASH_CHAR_OUT: VolAddress<u8, (), Safe> =
+  unsafe { VolAddress::new(0x04FF_FA1C) };
+
+/// Writes text to an emulator's debug output.
+///
+/// mGBA gets one message per line (long lines are split), each with the
+/// log's level. no$gba just gets the text. On hardware everything is thrown
+/// away, so it's fine to leave logging in a release build.
+///
+/// ```ignore
+/// let mut log = DebugLog::new().with_level(DebugLevel::Warn);
+/// writeln!(log, "{} enemies", count)?;
+/// ```
+#[derive(Debug)]
+pub struct DebugLog {
+  output: DebugOutput,
+  level: DebugLevel,
+  len: usize,
+}
+impl DebugLog {
+  /// Makes a log that goes to whatever [`DebugOutput::detect`] finds, at the
+  /// `Info` level.
+  #[inline]
+  #[must_use]
+  pub fn new() -> Self {
+    Self::with_output(DebugOutput::detect())
+  }
+  /// Makes a log that goes to the given output, at the `Info` level.
+  #[inline]
+  #[must_use]
+  pub const fn with_output(output: DebugOutput) -> Self {
+    Self { output, level: DebugLevel::Info, len: 0 }
+  }
+  /// Sets the level of messages sent after this.
+  #[inline]
+  #[must_use]
+  pub const fn with_level(self, level: DebugLevel) -> Self {
+    let out = Self { output: self.output, level, len: self.len };
+    // the partial line (if any) carries on in `out`, so don't flush it.
+    core::mem::forget(self);
+    out
+  }
+
+  /// Where the log's text goes.
+  #[inline]
+  #[must_use]
+  pub const fn output(&self) -> DebugOutput {
+    self.output
+  }
+
+  /// Sets the level of messages sent after this, sending any partial line
+  /// at the old level first.
+  #[inline]
+  pub fn set_level(&mut self, level: DebugLevel) {
+    self.flush();
+    self.level = level;
+  }
+
+  /// Sends any partial line.
+  pub fn flush(&mut self) {
+    if self.output == DebugOutput::Mgba && self.len > 0 {
+      // bit 8 sends the message.
+      MGBA_FLAGS.write(self.level as u16 | 0x100);
+    }
+    self.len = 0;
+  }
+}
+impl Default for DebugLog {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+impl Write for DebugLog {
+  fn write_str(&mut self, s: &str) -> core::fmt::Result {
+    match self.output {
+      DebugOutput::None => (),
+      DebugOutput::Mgba => {
+        for byte in s.bytes() {
+          if byte == b'\n' {
+            self.flush();
+            continue;
+          }
+          if self.len == MGBA_BUFFER.len() - 1 {
+            self.flush();
+          }
+          MGBA_BUFFER.index(self.len).write(byte);
+          self.len += 1;
+        }
+      }
+      DebugOutput::NoCash => s.bytes().for_each(|b| NOCASH_CHAR_OUT.write(b)),
+    }
+    Ok(())
+  }
+}
+impl Drop for DebugLog {
+  fn drop(&mut self) {
+    self.flush();
+  }
+}
+
+/// The `log` crate's logger, see [`init_debug_logger`].
+#[cfg(feature = "log")]
+#[derive(Debug)]
+struct DebugLogger {
+  output: GbaCell<u8>,
+}
+#[cfg(feature = "log")]
+static DEBUG_LOGGER: DebugLogger =
+  DebugLogger { output: unsafe { GbaCell::new(DebugOutput::None as u8) } };
+
+/// Detects the debug output and sends `log` crate records to it.
+///
+/// ```ignore
+/// gba::init_debug_logger(log::LevelFilter::Debug);
+/// log::info!("started");
+/// ```
+///
+/// Each record is written as one [`DebugLog`] message, with the `log` level
+/// mapped to the matching [`DebugLevel`]. Returns where the log is going. If
+/// nothing was found the logger is still set, and just throws everything away.
+#[cfg(feature = "log")]
+pub fn init_debug_logger(max_level: log::LevelFilter) -> DebugOutput {
+  let output = DebugOutput::detect();
+  DEBUG_LOGGER.output.write(output as u8);
+  critical_section(|_| {
+    // Safety: interrupts are off, so nothing else can be setting the logger
+    // at the same time.
+    unsafe {
+      let _ = log::set_logger_racy(&DEBUG_LOGGER);
+      log::set_max_level_racy(max_level);
+    }
+  });
+  output
+}
+
+#[cfg(feature = "log")]
+impl log::Log for DebugLogger {
+  fn enabled(&self, _metadata: &log::Metadata) -> bool {
+    self.output.read() != DebugOutput::None as u8
+  }
+
+  fn log(&self, record: &log::Record) {
+    let output = match self.output.read() {
+      1 => DebugOutput::Mgba,
+      2 => DebugOutput::NoCash,
+      _ => return,
+    };
+    let level = match record.level() {
+      log::Level::Error => DebugLevel::Error,
+      log::Level::Warn => DebugLevel::Warn,
+      log::Level::Info => DebugLevel::Info,
+      log::Level::Debug | log::Level::Trace => DebugLevel::Debug,
+    };
+    // an irq handler that logs would mix its text into this message.
+    critical_section(|_| {
+      let mut log = DebugLog::with_output(output).with_level(level);
+      let _ = write!(log, "[{}] {}", record.target(), record.args());
+      if output == DebugOutput::NoCash {
+        let _ = writeln!(log);
+      }
+    });
+  }
+
+  fn flush(&self) {}
+}
@@ -0,0 +1,243 @@
+//! A table of interrupt handlers, one per interrupt source.
+//!
+//! Instead of one function that gets every [`IrqBits`] and has to check them
+//! all, each source can have its own handler:
+//!
+//! ```ignore
+//! fn on_vblank() {
+//!   // ...
+//! }
+//! irq::register(IrqSource::VBlank, on_vblank);
+//! irq::enable(IrqSource::VBlank);
+//! ```
+//!
+//! Registering a handler installs the dispatcher with [`set_irq_handler`],
+//! so don't also set your own handler that way. Everything here turns
+//! interrupts off while it changes things, so it's all fine to call from
+//! main code while interrupts are on.
+
+use super::*;
+
+/// A handler for one interrupt source.
+///
+/// Handlers run with interrupts off (other than any allowed with
+/// [`set_nesting`]), so keep them short.
+pub type IrqHandler = fn();
+
+/// The things that can cause an interrupt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum IrqSource {
+  VBlank = 0,
+  HBlank = 1,
+  VCounter = 2,
+  Timer0 = 3,
+  Timer1 = 4,
+  Timer2 = 5,
+  Timer3 = 6,
+  Serial = 7,
+  Dma0 = 8,
+  Dma1 = 9,
+  Dma2 = 10,
+  Dma3 = 11,
+  Keypad = 12,
+  GamePak = 13,
+}
+impl IrqSource {
+  /// Every source, in bit order.
+  pub const ALL: [Self; SOURCE_COUNT] = [
+    Self::VBlank,
+    Self::HBlank,
+    Self::VCounter,
+    Self::Timer0,
+    Self::Timer1,
+    Self::Timer2,
+    Self::Timer3,
+    Self::Serial,
+    Self::Dma0,
+    Self::Dma1,
+    Self::Dma2,
+    Self::Dma3,
+    Self::Keypad,
+    Self::GamePak,
+  ];
+
+  /// The source's bit in `IE` and `IF`.
+  #[inline]
+  #[must_use]
+  pub const fn bits(self) -> IrqBits {
+    IrqBits(1 << (self as u16))
+  }
+
+  /// The peripheral register that has this source's irq enable bit, and
+  /// which bit it is.
+  ///
+  /// The display's bits are handled with [`DISPSTAT`] instead, and the game
+  /// pak doesn't have one.
+  #[inline]
+  #[must_use]
+  const fn enable_bit(self) -> Option<(usize, u16)> {
+    const TIMER_CNT_H: usize = 0x0400_0102;
+    const SIOCNT: usize = 0x0400_0128;
+    const DMA_CNT_H: usize = 0x0400_00BA;
+    match self {
+      Self::Timer0 | Self::Timer1 | Self::Timer2 | Self::Timer3 => {
+        let n = self as usize - Self::Timer0 as usize;
+        Some((TIMER_CNT_H + n * 4, 1 << 6))
+      }
+      Self::Serial => Some((SIOCNT, 1 << 14)),
+      Self::Dma0 | Self::Dma1 | Self::Dma2 | Self::Dma3 => {
+        let n = self as usize - Self::Dma0 as usize;
+        Some((DMA_CNT_H + n * 12, 1 << 14))
+      }
+      _ => None,
+    }
+  }
+}
+
+const SOURCE_COUNT: usize = 14;
+
+static HANDLERS: [GbaCell<Option<IrqHandler>>; SOURCE_COUNT] =
+  [const { unsafe { GbaCell::new(None) } }; SOURCE_COUNT];
+
+static PRIORITIES: [GbaCell<u8>; SOURCE_COUNT] =
+  [const { unsafe { GbaCell::new(0) } }; SOURCE_COUNT];
+
+/// Source numbers in the order that their handlers are called.
+static ORDER: [GbaCell<u8>; SOURCE_COUNT] = {
+  let mut order = [const { unsafe { GbaCell::new(0) } }; SOURCE_COUNT];
+  let mut i = 0;
+  while i < SOURCE_COUNT {
+    order[i] = unsafe { GbaCell::new(i as u8) };
+    i += 1;
+  }
+  order
+};
+
+/// The function given to [`set_irq_handler`], which calls the handler of
+/// each source that's pending.
+extern "C" fn dispatch(bits: IrqBits) {
+  for slot in ORDER.iter() {
+    let source = slot.read() as usize;
+    if bits.0 & (1 << source) != 0 {
+      if let Some(handler) = HANDLERS[source].read() {
+        handler();
+      }
+    }
+  }
+}
+
+/// Sets the handler for a source, giving back the old one.
+///
+/// This doesn't enable the interrupt, use [`enable`] for that.
+pub fn register(source: IrqSource, handler: IrqHandler) -> Option<IrqHandler> {
+  critical_section(|_| {
+    set_irq_handler(Some(dispatch));
+    let old = HANDLERS[source as usize].read();
+    HANDLERS[source as usize].write(Some(handler));
+    old
+  })
+}
+
+/// Removes the handler for a source, giving it back.
+///
+/// The interrupt is left enabled if it was, so [`disable`] it too if you
+/// don't want it happening at all.
+pub fn unregister(source: IrqSource) -> Option<IrqHandler> {
+  critical_section(|_| {
+    let old = HANDLERS[source as usize].read();
+    HANDLERS[source as usize].write(None);
+    old
+  })
+}
+
+/// Sets which sources can interrupt a handler that's already running.
+///
+/// See [`set_irq_nesting`] for the details. A typical use is letting the
+/// timer that refills the audio FIFO interrupt a long VBlank handler:
+///
+/// ```ignore
+/// irq::set_nesting(&[IrqSource::Timer1]);
+/// ```
+pub fn set_nesting(sources: &[IrqSource]) {
+  let mask = sources.iter().fold(0, |mask, s| mask | s.bits().0);
+  set_irq_nesting(IrqBits(mask));
+}
+
+/// Sets when a source's handler is called compared to the others, when more
+/// than one interrupt is pending at once.
+///
+/// Lower numbers are called first. All sources start at priority 0, and
+/// sources with the same priority are called in bit order.
+pub fn set_priority(source: IrqSource, priority: u8) {
+  critical_section(|_| {
+    PRIORITIES[source as usize].write(priority);
+    // an insertion sort, so equal priorities stay in bit order
+    let mut order = [0_u8; SOURCE_COUNT];
+    for (i, priority) in PRIORITIES.iter().enumerate() {
+      let p = priority.read();
+      let mut j = i;
+      while j > 0 && PRIORITIES[order[j - 1] as usize].read() > p {
+        order[j] = order[j - 1];
+        j -= 1;
+      }
+      order[j] = i as u8;
+    }
+    for (slot, s) in ORDER.iter().zip(order) {
+      slot.write(s);
+    }
+  });
+}
+
+/// The priority of a source, see [`set_priority`].
+#[inline]
+#[must_use]
+pub fn priority(source: IrqSource) -> u8 {
+  PRIORITIES[source as usize].read()
+}
+
+/// Turns on a source's interrupt.
+///
+/// This sets its bit in `IE`, and also the irq enable bit in the peripheral
+/// that causes it (such as [`DisplayStatus::with_vblank_irq`] or
+/// [`KeyInterruptControl::with_irq_enabled`]).
+pub fn enable(source: IrqSource) {
+  critical_section(|_| {
+    set_peripheral_enable(source, true);
+    IE.write(IrqBits(IE.read().0 | source.bits().0));
+  });
+}
+
+/// Turns off a source's interrupt, in `IE` and in the peripheral.
+pub fn disable(source: IrqSource) {
+  critical_section(|_| {
+    IE.write(IrqBits(IE.read().0 & !source.bits().0));
+    set_peripheral_enable(source, false);
+  });
+}
+
+/// If a source's bit is set in `IE`.
+#[inline]
+#[must_use]
+pub fn is_enabled(source: IrqSource) -> bool {
+  IE.read().0 & source.bits().0 != 0
+}
+
+fn set_peripheral_enable(source: IrqSource, on: bool) {
+  match source {
+    IrqSource::VBlank => DISPSTAT.write(DISPSTAT.read().with_vblank_irq(on)),
+    IrqSource::HBlank => DISPSTAT.write(DISPSTAT.read().with_hblank_irq(on)),
+    IrqSource::VCounter => {
+      DISPSTAT.write(DISPSTAT.read().with_vcounter_irq(on))
+    }
+    IrqSource::Keypad => KEYCNT.write(KEYCNT.read().with_irq_enabled(on)),
+    other => {
+      if let Some((addr, bit)) = other.enable_bit() {
+        // Safety: these are all readable and writable IO registers.
+        let reg: VolAddress<u16, Safe, Safe> = unsafe { VolAddress::new(addr) };
+        let v = reg.read();
+        reg.write(if on { v | bit } else { v & !bit });
+      }
+    }
+  }
+}
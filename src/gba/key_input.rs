@@ -21,6 +21,19 @@ impl Keys {
   u16_bool_field!(7, down, with_down);
   u16_bool_field!(8, r, with_r);
   u16_bool_field!(9, l, with_l);
+
+  /// If no keys are set.
+  #[inline]
+  #[must_use]
+  pub const fn is_empty(self) -> bool {
+    self.0 == 0
+  }
+  /// If every key set in `other` is also set in `self`.
+  #[inline]
+  #[must_use]
+  pub const fn contains(self, other: Self) -> bool {
+    self.0 & other.0 == other.0
+  }
 }
 impl From<KeysLowActive> for Keys {
   #[inline]
@@ -29,6 +42,37 @@ impl From<KeysLowActive> for Keys {
     Self(low.0 ^ 0b11_1111_1111)
   }
 }
+impl core::ops::BitAnd for Keys {
+  type Output = Self;
+  #[inline]
+  #[must_use]
+  fn bitand(self, rhs: Self) -> Self {
+    Self(self.0 & rhs.0)
+  }
+}
+impl core::ops::BitOr for Keys {
+  type Output = Self;
+  #[inline]
+  #[must_use]
+  fn bitor(self, rhs: Self) -> Self {
+    Self(self.0 | rhs.0)
+  }
+}
+impl core::ops::Not for Keys {
+  type Output = Self;
+  #[inline]
+  #[must_use]
+  fn not(self) -> Self {
+    Self(self.0 ^ 0b11_1111_1111)
+  }
+}
+impl From<u16> for Keys {
+  #[inline]
+  #[must_use]
+  fn from(u: u16) -> Self {
+    Self(u & 0b11_1111_1111)
+  }
+}
 impl From<Keys> for u16 {
   #[inline]
   #[must_use]
@@ -41,3 +85,155 @@ impl From<Keys> for u16 {
 pub fn get_keys() -> Keys {
   KEYINPUT.read().into()
 }
+
+/// If a key interrupt needs any of its keys or all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyIrqCondition {
+  /// Any one of the keys being held triggers the interrupt.
+  Any,
+  /// All of the keys must be held at once.
+  All,
+}
+
+/// Controls when the keypad interrupt happens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct KeyInterruptControl(u16);
+impl KeyInterruptControl {
+  const_new!();
+  u16_bool_field!(14, irq_enabled, with_irq_enabled);
+
+  /// If the interrupt needs any of the keys or all of them (bit 15).
+  #[inline]
+  #[must_use]
+  pub const fn condition(self) -> KeyIrqCondition {
+    if crate::u16_get_bit::<15>(self.0) {
+      KeyIrqCondition::All
+    } else {
+      KeyIrqCondition::Any
+    }
+  }
+  #[inline]
+  #[must_use]
+  pub const fn with_condition(self, condition: KeyIrqCondition) -> Self {
+    let all = matches!(condition, KeyIrqCondition::All);
+    Self(crate::u16_with_bit::<15>(self.0, all))
+  }
+
+  /// The keys that the interrupt watches.
+  #[inline]
+  #[must_use]
+  pub const fn keys(self) -> Keys {
+    Keys(self.0 & 0b11_1111_1111)
+  }
+  #[inline]
+  #[must_use]
+  pub const fn with_keys(self, keys: Keys) -> Self {
+    Self((self.0 & !0b11_1111_1111) | keys.0)
+  }
+}
+pub const KEYCNT: VolAddress<KeyInterruptControl, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0132) };
+
+/// Puts the GBA into its low power mode until all of `keys` are held.
+///
+/// This blanks the display, turns sound off, and uses the keypad interrupt
+/// to wake up from the BIOS [`Stop`] function. On waking, the display, sound
+/// control, interrupt, and `KEYCNT` settings are all put back how they were.
+///
+/// Turning sound off resets the registers of the four PSG channels, so any
+/// notes that were playing will need to be started again.
+///
+/// So that the keys used to go to sleep (or to wake up) aren't seen as
+/// presses by the game, this waits for `keys` to be released both before
+/// sleeping and before returning.
+///
+/// ## Panics
+/// * If `keys` is empty, since then nothing could wake the GBA.
+#[cfg(target_arch = "arm")]
+pub fn sleep_until_keys(keys: Keys) {
+  const SOUNDCNT_L: VolAddress<u16, Safe, Safe> =
+    unsafe { VolAddress::new(0x0400_0080) };
+  const SOUNDCNT_H: VolAddress<u16, Safe, Safe> =
+    unsafe { VolAddress::new(0x0400_0082) };
+  const SOUNDCNT_X: VolAddress<u16, Safe, Safe> =
+    unsafe { VolAddress::new(0x0400_0084) };
+  const SOUNDBIAS: VolAddress<u16, Safe, Safe> =
+    unsafe { VolAddress::new(0x0400_0088) };
+  assert!(!keys.is_empty(), "sleeping needs at least one wake key");
+
+  while !(get_keys() & keys).is_empty() {}
+
+  let ime = IME.read();
+  IME.write(false);
+  let ie = IE.read();
+  let keycnt = KEYCNT.read();
+  let dispcnt = DISPCNT.read();
+  let sound = [SOUNDCNT_L.read(), SOUNDCNT_H.read(), SOUNDBIAS.read()];
+  let sound_x = SOUNDCNT_X.read();
+
+  DISPCNT.write(dispcnt.with_forced_blank(true));
+  SOUNDCNT_X.write(0);
+  KEYCNT.write(
+    KeyInterruptControl::new()
+      .with_keys(keys)
+      .with_condition(KeyIrqCondition::All)
+      .with_irq_enabled(true),
+  );
+  IE.write(IrqBits::new().with_keypad(true));
+  IME.write(true);
+  Stop();
+  IME.write(false);
+
+  KEYCNT.write(keycnt);
+  IE.write(ie);
+  SOUNDCNT_X.write(sound_x);
+  SOUNDCNT_L.write(sound[0]);
+  SOUNDCNT_H.write(sound[1]);
+  SOUNDBIAS.write(sound[2]);
+  DISPCNT.write(dispcnt);
+  IME.write(ime);
+
+  while !(get_keys() & keys).is_empty() {}
+}
+
+#[cfg(all(test, not(target_arch = "arm")))]
+mod tests {
+  use super::*;
+  use quickcheck::quickcheck;
+
+  #[test]
+  fn key_interrupt_control_bits() {
+    let keys = Keys::new().with_a(true).with_l(true);
+    let control = KeyInterruptControl::new()
+      .with_keys(keys)
+      .with_condition(KeyIrqCondition::All)
+      .with_irq_enabled(true);
+    assert_eq!(control.0, 0b1100_0010_0000_0001);
+    assert_eq!(control.keys(), keys);
+    assert_eq!(control.condition(), KeyIrqCondition::All);
+    assert!(control.irq_enabled());
+    let control = control.with_condition(KeyIrqCondition::Any);
+    assert_eq!(control.0, 0b0100_0010_0000_0001);
+    assert_eq!(control.condition(), KeyIrqCondition::Any);
+    assert_eq!(KeyInterruptControl::new().condition(), KeyIrqCondition::Any);
+  }
+
+  quickcheck! {
+    fn key_interrupt_control_round_trips(
+      bits: u16, keys: u16, irq: bool, all: bool
+    ) -> bool {
+      let keys = Keys::from(keys);
+      let condition =
+        if all { KeyIrqCondition::All } else { KeyIrqCondition::Any };
+      let control = KeyInterruptControl(bits)
+        .with_keys(keys)
+        .with_irq_enabled(irq)
+        .with_condition(condition);
+      control.keys() == keys
+        && control.irq_enabled() == irq
+        && control.condition() == condition
+        && control.0 & 0b0011_1100_0000_0000 == bits & 0b0011_1100_0000_0000
+    }
+  }
+}
@@ -6,13 +6,26 @@ use crate::{
   Fx,
 };
 
+#[cfg(target_arch = "arm")]
 mod bios;
+#[cfg(target_arch = "arm")]
 pub use bios::*;
 
+mod background;
+pub use background::*;
+
 mod bg_charblock;
 pub use bg_charblock::*;
 
+mod critical;
+pub use critical::*;
+
+mod debug_log;
+pub use debug_log::*;
+
+#[cfg(target_arch = "arm")]
 mod default_art;
+#[cfg(target_arch = "arm")]
 pub use default_art::*;
 
 mod display_control;
@@ -21,15 +34,26 @@ pub use display_control::*;
 mod display_status;
 pub use display_status::*;
 
+#[cfg(target_arch = "arm")]
+pub mod irq;
+
 mod key_input;
 pub use key_input::*;
 
 mod palette;
 pub use palette::*;
 
+#[cfg(target_arch = "arm")]
+mod sections;
+#[cfg(target_arch = "arm")]
+pub use sections::*;
+
 mod text_screenblock;
 pub use text_screenblock::*;
 
+mod vram_plan;
+pub use vram_plan::*;
+
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct IrqBits(pub(crate) u16);
@@ -99,6 +123,7 @@ impl GbaCell<u32> {
 
 pub type RustIrqFn = extern "C" fn(IrqBits);
 
+#[cfg(target_arch = "arm")]
 #[inline(always)]
 pub fn set_irq_handler(opt_fn: Option<RustIrqFn>) {
   extern "C" {
@@ -108,6 +133,30 @@ pub fn set_irq_handler(opt_fn: Option<RustIrqFn>) {
   unsafe { RUST_IRQ_HANDLER.write(opt_fn) }
 }
 
+/// Sets which interrupt sources can interrupt the Rust irq handler.
+///
+/// By default (an empty mask) the handler runs with `IME` off, so any other
+/// interrupt has to wait until it returns. With a non-empty mask, while the
+/// handler runs `IE` is cut down to the sources in the mask and `IME` is
+/// turned on, so those sources can interrupt it. The sources being handled
+/// are always left out, so a handler never interrupts itself.
+///
+/// `IE` is put back when the handler returns, so in nested mode any change a
+/// handler makes to `IE` is lost.
+///
+/// Each level of nesting uses 32 bytes of the IRQ stack, which is only 160
+/// bytes by default, so keep the mask to a few sources that really need to
+/// be fast (such as the timers that refill the audio FIFOs).
+#[cfg(target_arch = "arm")]
+#[inline(always)]
+pub fn set_irq_nesting(mask: IrqBits) {
+  extern "C" {
+    static RUST_IRQ_NESTED_MASK: GbaCell<IrqBits>;
+  }
+  //
+  unsafe { RUST_IRQ_NESTED_MASK.write(mask) }
+}
+
 pub const BG0CNT: VolAddress<BgControl, Safe, Safe> =
   unsafe { VolAddress::new(0x0400_0008) };
 pub const BG1CNT: VolAddress<BgControl, Safe, Safe> =
@@ -117,7 +166,7 @@ pub const BG2CNT: VolAddress<BgControl, Safe, Safe> =
 pub const BG3CNT: VolAddress<BgControl, Safe, Safe> =
   unsafe { VolAddress::new(0x0400_000E) };
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct BgControl(u16);
 impl BgControl {
@@ -128,17 +177,99 @@ impl BgControl {
   u16_bool_field!(7, is_8bpp, with_8bpp);
   u16_value_field!(8 - 12, screenblock, with_screenblock);
   u16_bool_field!(13, affine_wrap, with_affine_wrap);
-  u16_value_field!(14 - 15, screen_size, with_screen_size);
+  u16_enum_field!(
+    14 - 15: TextScreenSize,
+    text_screen_size,
+    with_text_screen_size
+  );
+  u16_enum_field!(
+    14 - 15: AffineScreenSize,
+    affine_screen_size,
+    with_affine_screen_size
+  );
 }
 
-/*
-Internal Screen Size (dots) and size of BG Map (bytes):
-  Value  Text Mode (w,h)   Affine Mode
-  0      256x256 (2K)      128x128   (256 bytes)
-  1      512x256 (4K)      256x256   (1K)
-  2      256x512 (4K)      512x512   (4K)
-  3      512x512 (8K)      1024x1024 (16K)
-*/
+/// The size of a text mode background's map.
+///
+/// Each text screenblock is 32x32 tiles (2K), and the larger sizes just use
+/// more screenblocks in a row, starting from the background's base
+/// screenblock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum TextScreenSize {
+  /// 256x256 px, 1 screenblock.
+  Tiles32x32 = (0 << 14),
+  /// 512x256 px, 2 screenblocks (left, right).
+  Tiles64x32 = (1 << 14),
+  /// 256x512 px, 2 screenblocks (top, bottom).
+  Tiles32x64 = (2 << 14),
+  /// 512x512 px, 4 screenblocks (top left, top right, bottom left, bottom
+  /// right).
+  Tiles64x64 = (3 << 14),
+}
+impl TextScreenSize {
+  /// Width of the map, in tiles.
+  #[inline]
+  #[must_use]
+  pub const fn tiles_wide(self) -> usize {
+    match self {
+      Self::Tiles32x32 | Self::Tiles32x64 => 32,
+      Self::Tiles64x32 | Self::Tiles64x64 => 64,
+    }
+  }
+  /// Height of the map, in tiles.
+  #[inline]
+  #[must_use]
+  pub const fn tiles_tall(self) -> usize {
+    match self {
+      Self::Tiles32x32 | Self::Tiles64x32 => 32,
+      Self::Tiles32x64 | Self::Tiles64x64 => 64,
+    }
+  }
+  /// How many screenblocks in a row the map uses.
+  #[inline]
+  #[must_use]
+  pub const fn screenblock_count(self) -> usize {
+    (self.tiles_wide() / 32) * (self.tiles_tall() / 32)
+  }
+}
+
+/// The size of an affine background's map.
+///
+/// Affine maps are 1 byte per entry, so the smaller sizes don't fill an
+/// entire screenblock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum AffineScreenSize {
+  /// 128x128 px (256 bytes).
+  Tiles16x16 = (0 << 14),
+  /// 256x256 px (1K).
+  Tiles32x32 = (1 << 14),
+  /// 512x512 px (4K).
+  Tiles64x64 = (2 << 14),
+  /// 1024x1024 px (16K).
+  Tiles128x128 = (3 << 14),
+}
+impl AffineScreenSize {
+  /// Width (and height) of the map, in tiles.
+  #[inline]
+  #[must_use]
+  pub const fn tiles_wide(self) -> usize {
+    match self {
+      Self::Tiles16x16 => 16,
+      Self::Tiles32x32 => 32,
+      Self::Tiles64x64 => 64,
+      Self::Tiles128x128 => 128,
+    }
+  }
+  /// How many screenblocks the map touches.
+  #[inline]
+  #[must_use]
+  pub const fn screenblock_count(self) -> usize {
+    let bytes = self.tiles_wide() * self.tiles_wide();
+    bytes.div_ceil(2 * 1024)
+  }
+}
 
 pub type Tile4 = [u32; (4 * 8 * 8) / 32];
 pub type Tile8 = [u32; (8 * 8 * 8) / 32];
@@ -286,8 +417,9 @@ pub const PC: VolSeries<Fx<i16,8>, Safe, Safe, 32, { size_of::<[u16; 16]>() }> =
 pub const PD: VolSeries<Fx<i16,8>, Safe, Safe, 32, { size_of::<[u16; 16]>() }> =
   unsafe { VolSeries::new(0x0700_001E) };
 
+#[cfg(target_arch = "arm")]
 #[inline(never)]
-#[link_section = ".iwram"]
+#[link_section = ".iwram_text"]
 #[instruction_set(arm::a32)]
 pub unsafe fn a32_swp(in_val: u32, ptr: *mut u32) -> u32 {
   let out_val: u32;
@@ -300,8 +432,20 @@ pub unsafe fn a32_swp(in_val: u32, ptr: *mut u32) -> u32 {
   out_val
 }
 
+/// The host version of `a32_swp`, so that code using it can be tested off the
+/// GBA.
+///
+/// ## Safety
+/// * `ptr` must be valid to read and write, and aligned.
+#[cfg(not(target_arch = "arm"))]
+pub unsafe fn a32_swp(in_val: u32, ptr: *mut u32) -> u32 {
+  use core::sync::atomic::{AtomicU32, Ordering};
+  (*(ptr as *const AtomicU32)).swap(in_val, Ordering::SeqCst)
+}
+
+#[cfg(target_arch = "arm")]
 #[inline(never)]
-#[link_section = ".iwram"]
+#[link_section = ".iwram_text"]
 #[instruction_set(arm::a32)]
 pub unsafe fn a32_swpb(in_val: u8, ptr: *mut u8) -> u8 {
   let out_val: u8;
@@ -313,3 +457,14 @@ pub unsafe fn a32_swpb(in_val: u8, ptr: *mut u8) -> u8 {
   );
   out_val
 }
+
+/// The host version of `a32_swpb`, so that code using it can be tested off
+/// the GBA.
+///
+/// ## Safety
+/// * `ptr` must be valid to read and write, and aligned.
+#[cfg(not(target_arch = "arm"))]
+pub unsafe fn a32_swpb(in_val: u8, ptr: *mut u8) -> u8 {
+  use core::sync::atomic::{AtomicU8, Ordering};
+  (*(ptr as *const AtomicU8)).swap(in_val, Ordering::SeqCst)
+}
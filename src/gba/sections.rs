@@ -0,0 +1,73 @@
+/// How big each of the RAM sections from the linker script are, in bytes.
+///
+/// Statics go in IWRAM by default. Use `#[link_section = ".ewram"]` for a
+/// static that should go in EWRAM instead (or `".ewram_bss"` if it starts as
+/// all zeroes), and `#[link_section = ".iwram_text"]` along with
+/// `#[instruction_set(arm::a32)]` for a function that should run as ARM code
+/// from IWRAM (like [`a32_swp`](super::a32_swp)).
+///
+/// `arm-none-eabi-size -A` on the built program (see `examine.bat`) gives the
+/// same numbers without running anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectionSizes {
+  /// `.data`, `.iwram` and `.iwram_text`, which are copied in from ROM.
+  pub iwram_data: usize,
+  /// `.bss`, which starts as zeroes.
+  pub iwram_bss: usize,
+  /// `.ewram`, which is copied in from ROM.
+  pub ewram_data: usize,
+  /// `.ewram_bss`, which starts as zeroes.
+  pub ewram_bss: usize,
+}
+impl SectionSizes {
+  /// All of IWRAM, including the stacks and the BIOS area at the end.
+  pub const IWRAM_SIZE: usize = 32 * 1024;
+  /// All of EWRAM.
+  pub const EWRAM_SIZE: usize = 256 * 1024;
+
+  /// Gets the sizes that the linker picked.
+  #[must_use]
+  pub fn get() -> Self {
+    extern "C" {
+      static __data_start: u8;
+      static __data_end: u8;
+      static __bss_start: u8;
+      static __bss_end: u8;
+      static __ewram_start: u8;
+      static __ewram_end: u8;
+      static __ewram_bss_start: u8;
+      static __ewram_bss_end: u8;
+    }
+    // Safety: only the addresses are used.
+    unsafe {
+      let span = |start: &u8, end: &u8| {
+        end as *const u8 as usize - start as *const u8 as usize
+      };
+      Self {
+        iwram_data: span(&__data_start, &__data_end),
+        iwram_bss: span(&__bss_start, &__bss_end),
+        ewram_data: span(&__ewram_start, &__ewram_end),
+        ewram_bss: span(&__ewram_bss_start, &__ewram_bss_end),
+      }
+    }
+  }
+
+  /// How much of IWRAM the sections use.
+  ///
+  /// The stacks grow down from the end of IWRAM into whatever's left.
+  #[inline]
+  #[must_use]
+  pub const fn iwram_used(&self) -> usize {
+    self.iwram_data + self.iwram_bss
+  }
+
+  /// How much of EWRAM the sections use. The rest is what an [`Ewram`]
+  /// token covers.
+  ///
+  /// [`Ewram`]: crate::Ewram
+  #[inline]
+  #[must_use]
+  pub const fn ewram_used(&self) -> usize {
+    self.ewram_data + self.ewram_bss
+  }
+}
@@ -12,7 +12,7 @@ impl TextScreenEntry {
   #[inline]
   #[must_use]
   pub const fn from_id_bank(id: u16, palbank: u16) -> Self {
-    Self((id & 0x1_FF) | palbank << 12)
+    Self((id & 0x3_FF) | palbank << 12)
   }
 }
 
@@ -77,6 +77,14 @@ impl TextScreenblock {
     }
   }
 
+  /// The index of this screenblock, `0..32`.
+  #[inline]
+  #[must_use]
+  pub const fn index(self) -> usize {
+    (self.0.index(0).as_usize() - 0x0600_0000)
+      / size_of::<[TextScreenEntry; 32 * 32]>()
+  }
+
   #[inline]
   pub fn write_all(self, tse: TextScreenEntry) {
     self.0.iter().for_each(|va| va.write(tse))
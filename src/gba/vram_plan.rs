@@ -0,0 +1,191 @@
+use super::*;
+
+/// A plan for how background VRAM is split between tiles and maps.
+///
+/// Charblocks and screenblocks are two views of the same 64K of memory: each
+/// charblock is the same memory as 8 screenblocks in a row. Tile data can
+/// also run out of one charblock and into the next. The plan tracks all of
+/// this in units of screenblocks (2K), so that tiles and maps can be packed
+/// together without stepping on each other.
+///
+/// All the methods are `const`, so if you build the plan as a `const` then
+/// any overlap is a compile time error. Once the plan is built, get each
+/// layer's [`Background`] handle (or its parts) out of the plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VramPlan {
+  used: u32,
+  /// For each charblock with tiles declared, if they're 8bpp.
+  tiles: [Option<bool>; 4],
+  layers: [Option<Background>; 4],
+}
+impl VramPlan {
+  /// A plan with nothing in it.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self { used: 0, tiles: [None; 4], layers: [None; 4] }
+  }
+
+  /// Declares that `charblock` holds `count` tiles of data.
+  ///
+  /// The tiles are 4bpp unless `is_8bpp` is set. The tile data can extend
+  /// past the end of `charblock` into the following charblocks.
+  ///
+  /// ## Panics
+  /// * If the tiles run past the end of background VRAM.
+  /// * If the tiles overlap anything already in the plan.
+  #[inline]
+  #[must_use]
+  pub const fn with_tiles(
+    self, charblock: BgCharblock, count: usize, is_8bpp: bool,
+  ) -> Self {
+    if count > 1024 {
+      panic!("a background can only index 1024 tiles");
+    }
+    let tile_bytes = if is_8bpp { 64 } else { 32 };
+    let bytes = count * tile_bytes;
+    let blocks = bytes.div_ceil(2 * 1024);
+    let start = charblock.index() * 8;
+    if start + blocks > 32 {
+      panic!("tile data would run past the end of background VRAM");
+    }
+    if self.overlaps(start, blocks) {
+      panic!("tile data overlaps an earlier claim");
+    }
+    let mut out = self.claim(start, blocks);
+    out.tiles[charblock.index()] = Some(is_8bpp);
+    out
+  }
+
+  /// Declares a text background layer.
+  ///
+  /// The layer's map uses `size.screenblock_count()` screenblocks in a row
+  /// starting at `screenblock`, and its tiles come from `charblock`. The
+  /// layer is 8bpp if those tiles were declared as 8bpp.
+  ///
+  /// ## Panics
+  /// * If `index` is 4 or more.
+  /// * If that layer was already declared.
+  /// * If no tiles were declared at `charblock` with
+  ///   [`with_tiles`](Self::with_tiles).
+  /// * If the map would run past the last screenblock.
+  /// * If the map overlaps anything already in the plan.
+  #[inline]
+  #[must_use]
+  pub const fn with_text_bg(
+    self, index: usize, charblock: BgCharblock, screenblock: TextScreenblock,
+    size: TextScreenSize,
+  ) -> Self {
+    if index >= 4 {
+      panic!("background index must be less than 4");
+    }
+    if self.layers[index].is_some() {
+      panic!("background layer declared more than once");
+    }
+    let is_8bpp = match self.tiles[charblock.index()] {
+      Some(is_8bpp) => is_8bpp,
+      None => panic!("background uses a charblock with no tiles declared"),
+    };
+    let start = screenblock.index();
+    let blocks = size.screenblock_count();
+    if start + blocks > 32 {
+      panic!("map would run past the last screenblock");
+    }
+    if self.overlaps(start, blocks) {
+      panic!("map overlaps an earlier claim");
+    }
+    let mut out = self.claim(start, blocks);
+    let control = BgControl::new()
+      .with_charblock(charblock.index() as u16)
+      .with_8bpp(is_8bpp)
+      .with_screenblock(start as u16)
+      .with_text_screen_size(size);
+    out.layers[index] = Some(Background::from_parts(index, control));
+    out
+  }
+
+  /// Bit `n` is set if screenblock `n` is claimed by tiles or a map.
+  #[inline]
+  #[must_use]
+  pub const fn used_screenblocks(self) -> u32 {
+    self.used
+  }
+
+  /// The background handle for a layer.
+  ///
+  /// ## Panics
+  /// * If the layer wasn't declared.
+  #[inline]
+  #[must_use]
+  pub const fn background(self, index: usize) -> Background {
+    match self.try_background(index) {
+      Some(bg) => bg,
+      None => panic!("that background layer is not part of the plan"),
+    }
+  }
+
+  /// The background handle for a layer, if the layer was declared.
+  #[inline]
+  #[must_use]
+  pub const fn try_background(self, index: usize) -> Option<Background> {
+    if index < 4 {
+      self.layers[index]
+    } else {
+      None
+    }
+  }
+
+  /// The control register value for a layer.
+  ///
+  /// ## Panics
+  /// * If the layer wasn't declared.
+  #[inline]
+  #[must_use]
+  pub const fn bg_control(self, index: usize) -> BgControl {
+    self.background(index).control()
+  }
+
+  /// The charblock that a layer takes its tiles from.
+  ///
+  /// ## Panics
+  /// * If the layer wasn't declared.
+  #[inline]
+  #[must_use]
+  pub const fn charblock(self, index: usize) -> BgCharblock {
+    self.background(index).charblock()
+  }
+
+  /// The first screenblock of a layer's map.
+  ///
+  /// ## Panics
+  /// * If the layer wasn't declared.
+  #[inline]
+  #[must_use]
+  pub const fn screenblock(self, index: usize) -> TextScreenblock {
+    self.background(index).screenblock(0)
+  }
+
+  #[inline]
+  #[must_use]
+  const fn mask(start: usize, blocks: usize) -> u32 {
+    (((1_u64 << blocks) - 1) << start) as u32
+  }
+
+  #[inline]
+  #[must_use]
+  const fn overlaps(self, start: usize, blocks: usize) -> bool {
+    (self.used & Self::mask(start, blocks)) != 0
+  }
+
+  #[inline]
+  #[must_use]
+  const fn claim(self, start: usize, blocks: usize) -> Self {
+    Self { used: self.used | Self::mask(start, blocks), ..self }
+  }
+}
+impl Default for VramPlan {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
@@ -0,0 +1,264 @@
+//! A heap allocator that uses EWRAM.
+//!
+//! To use `alloc` types like `Vec` and `String`, make an [`EwramHeap`] the
+//! global allocator and give it the [`Ewram`] token at startup:
+//!
+//! ```ignore
+//! extern crate alloc;
+//!
+//! #[global_allocator]
+//! static HEAP: EwramHeap = EwramHeap::new();
+//!
+//! #[alloc_error_handler]
+//! fn alloc_error_handler(layout: core::alloc::Layout) -> ! {
+//!   out_of_memory(layout)
+//! }
+//!
+//! HEAP.init(Ewram::try_new().unwrap());
+//! let v: alloc::vec::Vec<u8> = alloc::vec![1, 2, 3];
+//! ```
+//!
+//! The heap is a first fit free list, with freed blocks merged back into
+//! their neighbours.
+
+use core::{
+  alloc::{GlobalAlloc, Layout},
+  mem::forget,
+  ptr::null_mut,
+};
+
+use crate::{
+  gba::{
+    critical_section, halt_forever, Color, DisplayControl, GbaMutex, PalRam,
+    DISPCNT,
+  },
+  Ewram,
+};
+
+/// Every block is a multiple of this many bytes, and aligned to it.
+const BLOCK_ALIGN: usize = 8;
+
+/// How full an [`EwramHeap`] is, all in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeapStats {
+  /// The size of the whole heap.
+  pub total: usize,
+  /// How much is allocated right now.
+  pub used: usize,
+  /// The most that's ever been allocated at once.
+  pub peak: usize,
+  /// The biggest free block, which is about the biggest single allocation
+  /// that would fit right now.
+  pub largest_free: usize,
+}
+
+/// A [`GlobalAlloc`] over the part of EWRAM that the linker doesn't use.
+///
+/// Allocations fail until [`init`](Self::init) has been called. Everything is
+/// done in a [`critical_section`], so irq handlers can allocate too.
+#[derive(Debug)]
+pub struct EwramHeap {
+  state: GbaMutex<HeapState>,
+}
+impl EwramHeap {
+  /// Makes a heap with no memory.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      state: GbaMutex::new(HeapState { free: 0, total: 0, used: 0, peak: 0 }),
+    }
+  }
+
+  /// Gives the heap its memory.
+  ///
+  /// The heap keeps the token forever, since anything allocated could be
+  /// anywhere in the token's memory.
+  ///
+  /// ## Panics
+  /// * If the heap already has its memory.
+  pub fn init(&self, ewram: Ewram) {
+    let (start, end) = Ewram::bounds();
+    forget(ewram);
+    let start = align_up(start, BLOCK_ALIGN);
+    let end = end & !(BLOCK_ALIGN - 1);
+    self.state.lock(|state| {
+      assert!(state.total == 0, "the heap was already given its memory");
+      if end > start {
+        // Safety: the token says we own this memory.
+        unsafe { state.add_memory(start, end - start) };
+      }
+    });
+  }
+
+  /// How full the heap is.
+  #[must_use]
+  pub fn stats(&self) -> HeapStats {
+    self.state.lock(|state| state.stats())
+  }
+}
+impl Default for EwramHeap {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+unsafe impl GlobalAlloc for EwramHeap {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    critical_section(|cs| {
+      self
+        .state
+        .try_lock_in(cs, |state| state.alloc(layout))
+        .unwrap_or(null_mut())
+    })
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    self.state.lock(|state| state.dealloc(ptr as usize, block_size(layout)));
+  }
+}
+
+/// Shows that an allocation failed and stops the program.
+///
+/// This is for the `#[alloc_error_handler]` of a program using
+/// [`EwramHeap`]. Every layer is turned off and the backdrop is painted
+/// magenta, so that running out of memory can be told apart from the red of
+/// a panic.
+pub fn out_of_memory(_layout: Layout) -> ! {
+  DISPCNT.write(DisplayControl::new());
+  PalRam::backdrop().write(Color::from_rgb(31, 0, 31));
+  halt_forever()
+}
+
+/// A free block's header, which is stored at the start of the block.
+#[repr(C)]
+struct FreeBlock {
+  size: usize,
+  /// The address of the next free block (in address order), or 0.
+  next: usize,
+}
+
+/// Free blocks are tracked by address, so that the state is `Send`.
+#[derive(Debug)]
+struct HeapState {
+  /// The address of the first free block, or 0.
+  free: usize,
+  total: usize,
+  used: usize,
+  peak: usize,
+}
+impl HeapState {
+  /// ## Safety
+  /// * The memory must be unused, and aligned to `BLOCK_ALIGN`.
+  unsafe fn add_memory(&mut self, start: usize, size: usize) {
+    self.total += size;
+    self.used += size;
+    self.dealloc(start, size);
+  }
+
+  unsafe fn alloc(&mut self, layout: Layout) -> *mut u8 {
+    let size = block_size(layout);
+    let align = layout.align().max(BLOCK_ALIGN);
+    let mut prev = 0;
+    let mut here = self.free;
+    while here != 0 {
+      let block = &*(here as *const FreeBlock);
+      let (block_size, next) = (block.size, block.next);
+      let start = align_up(here, align);
+      let front = start - here;
+      if front + size <= block_size {
+        let back = block_size - front - size;
+        // whatever is left over at the back becomes its own free block.
+        let after = if back > 0 {
+          let after = start + size;
+          write_block(after, back, next);
+          after
+        } else {
+          next
+        };
+        // and the space before the aligned start stays where it was.
+        if front > 0 {
+          write_block(here, front, after);
+        } else {
+          self.set_next(prev, after);
+        }
+        self.used += size;
+        self.peak = self.peak.max(self.used);
+        return start as *mut u8;
+      }
+      prev = here;
+      here = next;
+    }
+    null_mut()
+  }
+
+  /// Puts a block back on the free list, merging it with its neighbours.
+  unsafe fn dealloc(&mut self, addr: usize, size: usize) {
+    self.used -= size;
+    let mut prev = 0;
+    let mut next = self.free;
+    while next != 0 && next < addr {
+      prev = next;
+      next = (*(next as *const FreeBlock)).next;
+    }
+    let (mut addr, mut size) = (addr, size);
+    if next != 0 && addr + size == next {
+      let block = &*(next as *const FreeBlock);
+      size += block.size;
+      next = block.next;
+    }
+    if prev != 0 && prev + (*(prev as *const FreeBlock)).size == addr {
+      size += (*(prev as *const FreeBlock)).size;
+      addr = prev;
+    } else {
+      self.set_next(prev, addr);
+    }
+    write_block(addr, size, next);
+  }
+
+  /// Points `prev` (or the start of the list, if `prev` is 0) at `next`.
+  unsafe fn set_next(&mut self, prev: usize, next: usize) {
+    if prev == 0 {
+      self.free = next;
+    } else {
+      (*(prev as *mut FreeBlock)).next = next;
+    }
+  }
+
+  fn stats(&self) -> HeapStats {
+    let mut largest_free = 0;
+    let mut here = self.free;
+    while here != 0 {
+      // Safety: every address in the list is a free block.
+      let block = unsafe { &*(here as *const FreeBlock) };
+      largest_free = largest_free.max(block.size);
+      here = block.next;
+    }
+    HeapStats {
+      total: self.total,
+      used: self.used,
+      peak: self.peak,
+      largest_free,
+    }
+  }
+}
+
+#[inline]
+#[must_use]
+const fn align_up(addr: usize, align: usize) -> usize {
+  (addr + align - 1) & !(align - 1)
+}
+
+/// How much of the heap an allocation takes.
+#[inline]
+#[must_use]
+const fn block_size(layout: Layout) -> usize {
+  let size =
+    if layout.size() > BLOCK_ALIGN { layout.size() } else { BLOCK_ALIGN };
+  align_up(size, BLOCK_ALIGN)
+}
+
+#[inline]
+unsafe fn write_block(addr: usize, size: usize, next: usize) {
+  (addr as *mut FreeBlock).write(FreeBlock { size, next });
+}
@@ -0,0 +1,298 @@
+//! Tracking the keys from frame to frame.
+
+use crate::gba::{get_keys, Keys};
+
+/// How many recent presses a [`KeyState`] remembers for
+/// [`sequence`](KeyState::sequence).
+pub const INPUT_BUFFER_LEN: usize = 16;
+
+/// A+B+Start+Select, the usual chord for a soft reset.
+pub const SOFT_RESET_CHORD: Keys =
+  Keys::new().with_a(true).with_b(true).with_start(true).with_select(true);
+
+/// Keeps track of the keys across frames.
+///
+/// Call [`update`](Self::update) once per frame (usually right after VBlank)
+/// and then ask about what happened on that frame:
+/// * [`pressed`](Self::pressed) and [`released`](Self::released) are the keys
+///   that changed since the last frame.
+/// * [`held_frames`](Self::held_frames) is how long a key has been down.
+/// * [`repeated`](Self::repeated) is like `pressed`, but also fires again every
+///   so often while a key is held, which is good for menus.
+/// * [`chord`](Self::chord) checks for several keys held together.
+/// * [`sequence`](Self::sequence) checks for a series of presses, such as a
+///   fighting game special move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyState {
+  current: Keys,
+  previous: Keys,
+  held: [u16; 10],
+  repeat_delay: u16,
+  repeat_rate: u16,
+  frame: u32,
+  buffer: [(Keys, u32); INPUT_BUFFER_LEN],
+  buffer_newest: usize,
+  buffer_len: usize,
+}
+impl KeyState {
+  /// Makes a tracker with no keys held.
+  ///
+  /// Auto-repeat starts after 20 frames held, then repeats every 4 frames.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      current: Keys::new(),
+      previous: Keys::new(),
+      held: [0; 10],
+      repeat_delay: 20,
+      repeat_rate: 4,
+      frame: 0,
+      buffer: [(Keys::new(), 0); INPUT_BUFFER_LEN],
+      buffer_newest: 0,
+      buffer_len: 0,
+    }
+  }
+  /// Sets how many frames a key is held before it starts repeating, and how
+  /// many frames there are between repeats after that.
+  ///
+  /// A `rate` of 0 turns off auto-repeat.
+  #[inline]
+  #[must_use]
+  pub const fn with_repeat(self, delay: u16, rate: u16) -> Self {
+    Self { repeat_delay: delay, repeat_rate: rate, ..self }
+  }
+
+  /// Reads the keys with [`get_keys`] and moves to the next frame.
+  #[inline]
+  pub fn update(&mut self) {
+    self.update_with(get_keys());
+  }
+  /// Moves to the next frame, using the given keys as the ones held.
+  pub fn update_with(&mut self, keys: Keys) {
+    self.previous = self.current;
+    self.current = keys;
+    self.frame = self.frame.wrapping_add(1);
+    let bits = u16::from(keys);
+    for (i, held) in self.held.iter_mut().enumerate() {
+      *held = if bits & (1 << i) != 0 { held.saturating_add(1) } else { 0 };
+    }
+    let pressed = self.pressed();
+    if !pressed.is_empty() {
+      self.buffer_newest = (self.buffer_newest + 1) % INPUT_BUFFER_LEN;
+      self.buffer[self.buffer_newest] = (pressed, self.frame);
+      self.buffer_len = (self.buffer_len + 1).min(INPUT_BUFFER_LEN);
+    }
+  }
+
+  /// The keys held this frame.
+  #[inline]
+  #[must_use]
+  pub const fn current(&self) -> Keys {
+    self.current
+  }
+  /// The keys held last frame.
+  #[inline]
+  #[must_use]
+  pub const fn previous(&self) -> Keys {
+    self.previous
+  }
+  /// Keys that went down this frame.
+  #[inline]
+  #[must_use]
+  pub fn pressed(&self) -> Keys {
+    self.current & !self.previous
+  }
+  /// Keys that went up this frame.
+  #[inline]
+  #[must_use]
+  pub fn released(&self) -> Keys {
+    !self.current & self.previous
+  }
+
+  /// How many frames the given keys have all been held, counting this
+  /// frame.
+  ///
+  /// If more than one key is given, this is the shortest time any of them
+  /// has been held. It's 0 if any of them aren't held.
+  #[inline]
+  #[must_use]
+  pub fn held_frames(&self, keys: Keys) -> u16 {
+    let bits = u16::from(keys);
+    (0..10)
+      .filter(|i| bits & (1 << i) != 0)
+      .map(|i| self.held[i])
+      .min()
+      .unwrap_or(0)
+  }
+
+  /// Keys that were pressed this frame, or that are due to repeat this frame
+  /// because they've been held down.
+  #[must_use]
+  pub fn repeated(&self) -> Keys {
+    let mut bits = 0;
+    for (i, held) in self.held.iter().copied().enumerate() {
+      let fires = held == 1
+        || (self.repeat_rate > 0
+          && held > self.repeat_delay
+          && (held - 1 - self.repeat_delay).is_multiple_of(self.repeat_rate));
+      if fires {
+        bits |= 1 << i;
+      }
+    }
+    Keys::from(bits)
+  }
+
+  /// If all of the keys are held, and at least one of them was pressed this
+  /// frame.
+  ///
+  /// This is true just on the frame the chord is completed, so it's good for
+  /// things like a soft reset with [`SOFT_RESET_CHORD`].
+  #[inline]
+  #[must_use]
+  pub fn chord(&self, keys: Keys) -> bool {
+    !keys.is_empty()
+      && self.current.contains(keys)
+      && !(self.pressed() & keys).is_empty()
+  }
+
+  /// If the most recent presses match `steps`, with the last step pressed
+  /// this frame and all of them within `window` frames.
+  ///
+  /// Each step matches a frame's presses if all of the step's keys were
+  /// pressed on that frame, so a step can be a single key or several keys
+  /// pressed together. Frames with no presses don't count, but any other
+  /// press in between breaks the sequence.
+  ///
+  /// Only the last [`INPUT_BUFFER_LEN`] presses are kept, so longer
+  /// sequences never match.
+  #[must_use]
+  pub fn sequence(&self, steps: &[Keys], window: u32) -> bool {
+    if steps.is_empty() || steps.len() > self.buffer_len {
+      return false;
+    }
+    let entry = |back: usize| {
+      self.buffer
+        [(self.buffer_newest + INPUT_BUFFER_LEN - back) % INPUT_BUFFER_LEN]
+    };
+    let (_, last_frame) = entry(0);
+    let (_, first_frame) = entry(steps.len() - 1);
+    if last_frame != self.frame || last_frame.wrapping_sub(first_frame) > window
+    {
+      return false;
+    }
+    steps
+      .iter()
+      .rev()
+      .enumerate()
+      .all(|(back, step)| entry(back).0.contains(*step))
+  }
+
+  /// Forgets all buffered presses, such as once a sequence has been used.
+  #[inline]
+  pub fn clear_buffer(&mut self) {
+    self.buffer_len = 0;
+  }
+}
+impl Default for KeyState {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[cfg(all(test, not(target_arch = "arm")))]
+mod tests {
+  extern crate std;
+
+  use super::*;
+  use std::{vec, vec::Vec};
+
+  const A: Keys = Keys::new().with_a(true);
+  const B: Keys = Keys::new().with_b(true);
+
+  /// Holds `keys` for `frames` frames, giving the frames (counting from 1)
+  /// that `keys` repeated on.
+  fn repeat_frames(state: &mut KeyState, keys: Keys, frames: u16) -> Vec<u16> {
+    (1..=frames)
+      .filter(|_| {
+        state.update_with(keys);
+        state.repeated().contains(keys)
+      })
+      .collect()
+  }
+
+  #[test]
+  fn repeat_waits_for_the_delay_then_keeps_the_rate() {
+    let mut state = KeyState::new().with_repeat(3, 2);
+    assert_eq!(repeat_frames(&mut state, A, 10), vec![1, 4, 6, 8, 10]);
+  }
+
+  #[test]
+  fn default_repeat_is_20_then_every_4() {
+    let mut state = KeyState::new();
+    assert_eq!(repeat_frames(&mut state, A, 30), vec![1, 21, 25, 29]);
+  }
+
+  #[test]
+  fn zero_rate_only_fires_on_the_press() {
+    let mut state = KeyState::new().with_repeat(0, 0);
+    assert_eq!(repeat_frames(&mut state, A, 10), vec![1]);
+  }
+
+  #[test]
+  fn releasing_restarts_the_delay() {
+    let mut state = KeyState::new().with_repeat(2, 1);
+    assert_eq!(repeat_frames(&mut state, A, 4), vec![1, 3, 4]);
+    state.update_with(Keys::new());
+    assert!(state.repeated().is_empty());
+    assert_eq!(repeat_frames(&mut state, A, 4), vec![1, 3, 4]);
+  }
+
+  #[test]
+  fn keys_repeat_on_their_own_schedules() {
+    let mut state = KeyState::new().with_repeat(2, 2);
+    state.update_with(A);
+    state.update_with(A);
+    state.update_with(A | B);
+    assert_eq!(state.repeated(), A | B);
+    state.update_with(A | B);
+    assert!(state.repeated().is_empty());
+    state.update_with(A | B);
+    assert_eq!(state.repeated(), A | B);
+    assert_eq!(state.held_frames(A), 5);
+    assert_eq!(state.held_frames(A | B), 3);
+  }
+
+  #[test]
+  fn pressed_released_and_chord() {
+    let mut state = KeyState::new();
+    state.update_with(A);
+    assert_eq!(state.pressed(), A);
+    assert!(!state.chord(A | B));
+    state.update_with(A | B);
+    assert_eq!(state.pressed(), B);
+    assert!(state.chord(A | B));
+    state.update_with(A | B);
+    assert!(!state.chord(A | B));
+    state.update_with(B);
+    assert_eq!(state.released(), A);
+  }
+
+  #[test]
+  fn sequence_needs_the_steps_in_order_within_the_window() {
+    let mut state = KeyState::new();
+    for keys in [A, Keys::new(), B, Keys::new(), A] {
+      state.update_with(keys);
+    }
+    assert!(state.sequence(&[A, B, A], 4));
+    assert!(!state.sequence(&[A, B, A], 3));
+    assert!(!state.sequence(&[B, A, A], 4));
+    state.update_with(Keys::new());
+    assert!(!state.sequence(&[A, B, A], 4));
+    state.clear_buffer();
+    state.update_with(A);
+    assert!(!state.sequence(&[B, A], 10));
+    assert!(state.sequence(&[A], 0));
+  }
+}
@@ -1,7 +1,29 @@
+//! ## Testing
+//!
+//! `cargo test` builds the tests into GBA ROMs and runs them in mGBA (see
+//! [`testing`]). Most of the crate is plain logic, so its unit tests also
+//! run on the host with `cargo test-host` (an alias in `.cargo/config.toml`).
+//!
+//! Anything that needs the GBA itself (inline assembly, the BIOS, and the
+//! linker script's symbols) is behind `#[cfg(target_arch = "arm")]`. The
+//! MMIO definitions still build on the host, they just can't be used there.
+//! Host only tests are in `#[cfg(all(test, not(target_arch = "arm")))]`
+//! modules.
+
 #![no_std]
 #![feature(asm)]
 #![feature(asm_const)]
 #![feature(isa_attribute)]
+#![cfg_attr(all(test, target_arch = "arm"), no_main)]
+#![cfg_attr(all(test, target_arch = "arm"), feature(custom_test_frameworks))]
+#![cfg_attr(
+  all(test, target_arch = "arm"),
+  test_runner(crate::testing::test_runner)
+)]
+#![cfg_attr(
+  all(test, target_arch = "arm"),
+  reexport_test_harness_main = "test_main"
+)]
 
 mod macros;
 
@@ -12,14 +34,79 @@ pub use bit_utils::*;
 pub mod gba;
 use gba::{a32_swpb, GbaCell};
 
+pub mod ansi;
+pub use ansi::*;
+
+pub mod cp437;
+pub use cp437::*;
+
+pub mod dialog_box;
+pub use dialog_box::*;
+
 pub mod fixed_point;
 pub use fixed_point::*;
 
+#[cfg(target_arch = "arm")]
+pub mod heap;
+#[cfg(target_arch = "arm")]
+pub use heap::*;
+
+pub mod input;
+pub use input::*;
+
+pub mod line_editor;
+pub use line_editor::*;
+
+pub mod on_screen_keyboard;
+pub use on_screen_keyboard::*;
+
+#[cfg(target_arch = "arm")]
+pub mod panic_screen;
+#[cfg(target_arch = "arm")]
+pub use panic_screen::*;
+
+pub mod parallax;
+pub use parallax::*;
+
+pub mod scrollback;
+pub use scrollback::*;
+
+pub mod scrolling_map;
+pub use scrolling_map::*;
+
+pub mod shell;
+pub use shell::*;
+
+pub mod spsc_queue;
+pub use spsc_queue::*;
+
+#[cfg(target_arch = "arm")]
+pub mod testing;
+
+pub mod text_canvas;
+pub use text_canvas::*;
+
+pub mod text_console;
+pub use text_console::*;
+
+pub mod text_layout;
+pub use text_layout::*;
+
+pub mod widgets;
+pub use widgets::*;
+
 static EWRAM_STATE: GbaCell<u8> = unsafe { GbaCell::new(0) };
+/// Ownership of the free part of EWRAM.
+///
+/// Statics in the `.ewram` and `.ewram_bss` sections are at the start of
+/// EWRAM, so the token only covers the memory after them (all 256K if there
+/// aren't any). See [`bounds`](Self::bounds).
+///
+/// Built for the host (for the tests) there's no EWRAM, and the token covers
+/// nothing.
+#[derive(Debug)]
 pub struct Ewram(());
 impl Ewram {
-  const EWRAM_BASE: usize = 0x0200_0000;
-
   pub fn try_new() -> Option<Self> {
     if unsafe { a32_swpb(1, EWRAM_STATE.get_ptr()) } != 0 {
       None
@@ -27,6 +114,38 @@ impl Ewram {
       Some(Self(()))
     }
   }
+
+  /// The start and end addresses of the memory that the token covers.
+  ///
+  /// These are `__ewram_heap_start` and `__ewram_heap_end` from the linker
+  /// script, which always go from the end of the EWRAM statics to the end of
+  /// EWRAM.
+  #[cfg(target_arch = "arm")]
+  #[inline]
+  #[must_use]
+  pub fn bounds() -> (usize, usize) {
+    extern "C" {
+      static __ewram_heap_start: u32;
+      static __ewram_heap_end: u32;
+    }
+    // Safety: only the addresses of the symbols are used.
+    unsafe {
+      (
+        &__ewram_heap_start as *const u32 as usize,
+        &__ewram_heap_end as *const u32 as usize,
+      )
+    }
+  }
+  /// The start and end addresses of the memory that the token covers.
+  ///
+  /// On the host this is an empty range.
+  #[cfg(not(target_arch = "arm"))]
+  #[inline]
+  #[must_use]
+  pub fn bounds() -> (usize, usize) {
+    let start = core::ptr::NonNull::<u32>::dangling().as_ptr() as usize;
+    (start, start)
+  }
 }
 impl core::ops::Drop for Ewram {
   fn drop(&mut self) {
@@ -34,13 +153,19 @@ impl core::ops::Drop for Ewram {
   }
 }
 impl core::ops::Deref for Ewram {
-  type Target = [u32; 65536];
+  type Target = [u32];
   fn deref(&self) -> &Self::Target {
-    unsafe { &*(Self::EWRAM_BASE as *const Self::Target) }
+    let (start, end) = Self::bounds();
+    unsafe {
+      core::slice::from_raw_parts(start as *const u32, (end - start) / 4)
+    }
   }
 }
 impl core::ops::DerefMut for Ewram {
   fn deref_mut(&mut self) -> &mut Self::Target {
-    unsafe { &mut *(Self::EWRAM_BASE as *mut Self::Target) }
+    let (start, end) = Self::bounds();
+    unsafe {
+      core::slice::from_raw_parts_mut(start as *mut u32, (end - start) / 4)
+    }
   }
 }
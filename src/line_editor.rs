@@ -0,0 +1,192 @@
+//! Editing a single line of text, with history.
+
+use crate::KeyboardEvent;
+
+/// How many old lines a [`LineEditor`] remembers.
+pub const LINE_HISTORY: usize = 8;
+
+/// A one line text buffer of up to `N` bytes, with a cursor and history.
+///
+/// Only printable ASCII is accepted, so the line is always a valid `&str`.
+///
+/// When a line is submitted it's added to the history (unless it's blank or
+/// the same as the previous line), and the history can be stepped through
+/// with [`history_prev`](Self::history_prev) and
+/// [`history_next`](Self::history_next).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineEditor<const N: usize> {
+  buf: [u8; N],
+  len: usize,
+  cursor: usize,
+  history: [[u8; N]; LINE_HISTORY],
+  history_lens: [usize; LINE_HISTORY],
+  history_count: usize,
+  history_newest: usize,
+  /// How far back into the history we are, 0 means the line being typed.
+  browse: usize,
+}
+impl<const N: usize> LineEditor<N> {
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      buf: [0; N],
+      len: 0,
+      cursor: 0,
+      history: [[0; N]; LINE_HISTORY],
+      history_lens: [0; LINE_HISTORY],
+      history_count: 0,
+      history_newest: 0,
+      browse: 0,
+    }
+  }
+
+  /// The current line.
+  #[inline]
+  #[must_use]
+  pub fn line(&self) -> &str {
+    // only printable ASCII is ever put into the buffer
+    core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+  }
+  /// The cursor position, in bytes from the start of the line.
+  #[inline]
+  #[must_use]
+  pub const fn cursor(&self) -> usize {
+    self.cursor
+  }
+  #[inline]
+  #[must_use]
+  pub const fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Inserts a byte at the cursor.
+  ///
+  /// ## Failure
+  /// * If the byte isn't printable ASCII, or the line is full.
+  #[inline]
+  pub fn insert(&mut self, byte: u8) -> bool {
+    if !(b' '..=b'~').contains(&byte) || self.len >= N {
+      return false;
+    }
+    self.buf.copy_within(self.cursor..self.len, self.cursor + 1);
+    self.buf[self.cursor] = byte;
+    self.len += 1;
+    self.cursor += 1;
+    true
+  }
+  /// Deletes the byte before the cursor.
+  #[inline]
+  pub fn backspace(&mut self) {
+    if self.cursor > 0 {
+      self.cursor -= 1;
+      self.delete();
+    }
+  }
+  /// Deletes the byte at the cursor.
+  #[inline]
+  pub fn delete(&mut self) {
+    if self.cursor < self.len {
+      self.buf.copy_within(self.cursor + 1..self.len, self.cursor);
+      self.len -= 1;
+    }
+  }
+  #[inline]
+  pub fn move_left(&mut self) {
+    self.cursor = self.cursor.saturating_sub(1);
+  }
+  #[inline]
+  pub fn move_right(&mut self) {
+    self.cursor = (self.cursor + 1).min(self.len);
+  }
+  #[inline]
+  pub fn home(&mut self) {
+    self.cursor = 0;
+  }
+  #[inline]
+  pub fn end(&mut self) {
+    self.cursor = self.len;
+  }
+  /// Empties the line.
+  #[inline]
+  pub fn clear(&mut self) {
+    self.len = 0;
+    self.cursor = 0;
+    self.browse = 0;
+  }
+
+  /// Replaces the line with the next older line from the history.
+  #[inline]
+  pub fn history_prev(&mut self) {
+    if self.browse < self.history_count {
+      self.browse += 1;
+      self.load_history();
+    }
+  }
+  /// Replaces the line with the next newer line from the history, or a blank
+  /// line after the newest.
+  #[inline]
+  pub fn history_next(&mut self) {
+    if self.browse > 0 {
+      self.browse -= 1;
+      self.load_history();
+    }
+  }
+
+  /// Finishes the line, giving it back and starting a new blank line.
+  pub fn submit(&mut self) -> &str {
+    let len = self.len;
+    self.clear();
+    let is_repeat = self.history_count > 0 && {
+      let newest = self.history_newest;
+      self.history[newest][..self.history_lens[newest]] == self.buf[..len]
+    };
+    if len > 0 && !is_repeat {
+      if self.history_count > 0 {
+        self.history_newest = (self.history_newest + 1) % LINE_HISTORY;
+      }
+      self.history[self.history_newest][..len]
+        .copy_from_slice(&self.buf[..len]);
+      self.history_lens[self.history_newest] = len;
+      self.history_count = (self.history_count + 1).min(LINE_HISTORY);
+    }
+    core::str::from_utf8(&self.buf[..len]).unwrap_or("")
+  }
+
+  /// Applies a keyboard event to the line.
+  ///
+  /// If the event submits the line, the finished line is returned.
+  pub fn apply_keyboard_event(&mut self, event: KeyboardEvent) -> Option<&str> {
+    match event {
+      KeyboardEvent::Char(byte) => {
+        self.insert(byte);
+      }
+      KeyboardEvent::Backspace => self.backspace(),
+      KeyboardEvent::CursorLeft => self.move_left(),
+      KeyboardEvent::CursorRight => self.move_right(),
+      KeyboardEvent::HistoryPrev => self.history_prev(),
+      KeyboardEvent::HistoryNext => self.history_next(),
+      KeyboardEvent::Submit => return Some(self.submit()),
+    }
+    None
+  }
+
+  #[inline]
+  fn load_history(&mut self) {
+    if self.browse == 0 {
+      self.len = 0;
+    } else {
+      let i =
+        (self.history_newest + LINE_HISTORY - (self.browse - 1)) % LINE_HISTORY;
+      self.len = self.history_lens[i];
+      self.buf[..self.len].copy_from_slice(&self.history[i][..self.len]);
+    }
+    self.cursor = self.len;
+  }
+}
+impl<const N: usize> Default for LineEditor<N> {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
@@ -65,3 +65,51 @@ macro_rules! u16_bool_field {
   };
 }
 pub(crate) use u16_bool_field;
+
+#[cfg(all(test, not(target_arch = "arm")))]
+mod tests {
+  use quickcheck::quickcheck;
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+  #[repr(u16)]
+  enum Speed {
+    Slow = 0 << 4,
+    Medium = 1 << 4,
+    Fast = 2 << 4,
+    Fastest = 3 << 4,
+  }
+  const SPEEDS: [Speed; 4] =
+    [Speed::Slow, Speed::Medium, Speed::Fast, Speed::Fastest];
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+  struct Fields(u16);
+  impl Fields {
+    const_new!();
+    u16_bool_field!(0, flag, with_flag);
+    u16_enum_field!(4 - 5: Speed, speed, with_speed);
+    u16_value_field!(8 - 12, amount, with_amount);
+  }
+
+  quickcheck! {
+    fn starts_at_zero() -> bool {
+      Fields::new() == Fields(0)
+    }
+
+    fn fields_round_trip(x: u16, flag: bool, speed: u8, amount: u16) -> bool {
+      let speed = SPEEDS[usize::from(speed % 4)];
+      let f = Fields(x).with_flag(flag).with_speed(speed).with_amount(amount);
+      f.flag() == flag
+        && f.speed() == speed
+        && f.amount() == amount & 0b1_1111
+        && f.0 & !0b1_1111_0011_0001 == x & !0b1_1111_0011_0001
+    }
+
+    fn setting_a_field_leaves_the_others(x: u16, amount: u16) -> bool {
+      let before = Fields(x);
+      let after = before.with_amount(amount);
+      after.flag() == before.flag()
+        && after.speed() == before.speed()
+        && after.with_amount(before.amount()) == before
+    }
+  }
+}
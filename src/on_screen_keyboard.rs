@@ -0,0 +1,237 @@
+//! A D-pad driven keyboard drawn on a text background.
+
+use crate::gba::{Keys, TextScreenEntry, TextScreenblock};
+
+const LOWER: [&[u8]; 4] =
+  [b"1234567890", b"qwertyuiop", b"asdfghjkl", b"zxcvbnm,. "];
+const UPPER: [&[u8]; 4] =
+  [b"1234567890", b"QWERTYUIOP", b"ASDFGHJKL", b"ZXCVBNM,. "];
+const SYMBOLS: [&[u8]; 4] =
+  [b"!\"#$%&'()*", b"+-/:;<=>?@", b"[\\]^_`{|}~", b" "];
+
+/// The CP437 glyph used to draw the space key.
+const SPACE_GLYPH: u8 = 0x16;
+
+/// Which set of keys the keyboard is showing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardPage {
+  Lower,
+  Upper,
+  Symbols,
+}
+impl KeyboardPage {
+  #[inline]
+  #[must_use]
+  const fn rows(self) -> &'static [&'static [u8]; 4] {
+    match self {
+      Self::Lower => &LOWER,
+      Self::Upper => &UPPER,
+      Self::Symbols => &SYMBOLS,
+    }
+  }
+  #[inline]
+  #[must_use]
+  const fn next(self) -> Self {
+    match self {
+      Self::Lower => Self::Upper,
+      Self::Upper => Self::Symbols,
+      Self::Symbols => Self::Lower,
+    }
+  }
+}
+
+/// Something the user did with the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardEvent {
+  /// A typed a key.
+  Char(u8),
+  /// B was pressed.
+  Backspace,
+  /// L was pressed.
+  CursorLeft,
+  /// R was pressed.
+  CursorRight,
+  /// Up was pressed while on the top row.
+  HistoryPrev,
+  /// Down was pressed while on the bottom row.
+  HistoryNext,
+  /// Start was pressed.
+  Submit,
+}
+
+/// A grid of keys, moved around with the D-pad.
+///
+/// * The D-pad moves the selection (and Up/Down past the top and bottom rows
+///   step through the line history).
+/// * A types the selected key, B is backspace.
+/// * L and R move the text cursor.
+/// * Start submits the line.
+/// * Select switches between lower case, upper case, and symbols.
+///
+/// Each key takes two tiles across, so the keyboard is 20 tiles wide and 4
+/// tall. The selected key is drawn with the highlight palbank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnScreenKeyboard {
+  page: KeyboardPage,
+  x: usize,
+  y: usize,
+  dirty: bool,
+  screenblock: TextScreenblock,
+  origin_x: usize,
+  origin_y: usize,
+  font_offset: u16,
+  palbank: u16,
+  highlight_palbank: u16,
+}
+impl OnScreenKeyboard {
+  /// The width of the keyboard, in tiles.
+  pub const WIDTH: usize = 20;
+  /// The height of the keyboard, in tiles.
+  pub const HEIGHT: usize = 4;
+
+  /// Makes a keyboard in the top left of the screenblock.
+  #[inline]
+  #[must_use]
+  pub const fn new(screenblock: TextScreenblock) -> Self {
+    Self {
+      page: KeyboardPage::Lower,
+      x: 0,
+      y: 0,
+      dirty: true,
+      screenblock,
+      origin_x: 0,
+      origin_y: 0,
+      font_offset: 0,
+      palbank: 0,
+      highlight_palbank: 1,
+    }
+  }
+  /// Moves where the keyboard's top left corner is in the screenblock.
+  ///
+  /// ## Panics
+  /// * If the keyboard wouldn't fit in the screenblock.
+  #[inline]
+  #[must_use]
+  pub const fn with_origin(self, x: usize, y: usize) -> Self {
+    assert!(
+      x + Self::WIDTH <= 32 && y + Self::HEIGHT <= 32,
+      "keyboard must fit in the screenblock"
+    );
+    Self { origin_x: x, origin_y: y, dirty: true, ..self }
+  }
+  /// Sets the tile index of the font's first glyph.
+  #[inline]
+  #[must_use]
+  pub const fn with_font_offset(self, font_offset: u16) -> Self {
+    Self { font_offset, dirty: true, ..self }
+  }
+  /// Sets the palbanks for normal keys and for the selected key.
+  #[inline]
+  #[must_use]
+  pub const fn with_palbanks(self, normal: u16, highlight: u16) -> Self {
+    Self {
+      palbank: normal & 0xF,
+      highlight_palbank: highlight & 0xF,
+      dirty: true,
+      ..self
+    }
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn page(&self) -> KeyboardPage {
+    self.page
+  }
+  /// The byte of the selected key.
+  #[inline]
+  #[must_use]
+  pub const fn selected(&self) -> u8 {
+    self.page.rows()[self.y][self.x]
+  }
+
+  /// Handles newly pressed keys.
+  pub fn update(&mut self, pressed: Keys) -> Option<KeyboardEvent> {
+    let rows = self.page.rows();
+    let mut event = None;
+    if pressed.left() {
+      let row_len = rows[self.y].len();
+      self.x = (self.x + row_len - 1) % row_len;
+      self.dirty = true;
+    }
+    if pressed.right() {
+      self.x = (self.x + 1) % rows[self.y].len();
+      self.dirty = true;
+    }
+    if pressed.up() {
+      if self.y == 0 {
+        event = Some(KeyboardEvent::HistoryPrev);
+      } else {
+        self.y -= 1;
+      }
+      self.dirty = true;
+    }
+    if pressed.down() {
+      if self.y + 1 == rows.len() {
+        event = Some(KeyboardEvent::HistoryNext);
+      } else {
+        self.y += 1;
+      }
+      self.dirty = true;
+    }
+    if pressed.select() {
+      self.page = self.page.next();
+      self.dirty = true;
+    }
+    self.x = self.x.min(self.page.rows()[self.y].len() - 1);
+    if pressed.l() {
+      event = Some(KeyboardEvent::CursorLeft);
+    }
+    if pressed.r() {
+      event = Some(KeyboardEvent::CursorRight);
+    }
+    if pressed.b() {
+      event = Some(KeyboardEvent::Backspace);
+    }
+    if pressed.a() {
+      event = Some(KeyboardEvent::Char(self.selected()));
+    }
+    if pressed.start() {
+      event = Some(KeyboardEvent::Submit);
+    }
+    event
+  }
+
+  /// Draws the keyboard, if it's changed since it was last drawn.
+  ///
+  /// Call this during VBlank.
+  pub fn draw(&mut self) {
+    if !self.dirty {
+      return;
+    }
+    self.dirty = false;
+    let block = self.screenblock.as_volblock();
+    for (y, row) in self.page.rows().iter().enumerate() {
+      let row_start = (self.origin_y + y) * 32 + self.origin_x;
+      for x in 0..(Self::WIDTH / 2) {
+        let (glyph, palbank) = match row.get(x).copied() {
+          Some(b' ') => (SPACE_GLYPH, self.palbank),
+          Some(byte) => (byte, self.palbank),
+          None => (b' ', self.palbank),
+        };
+        let palbank = if (x, y) == (self.x, self.y) {
+          self.highlight_palbank
+        } else {
+          palbank
+        };
+        let id = self.font_offset.wrapping_add(glyph as u16);
+        let blank = self.font_offset.wrapping_add(b' ' as u16);
+        block
+          .index(row_start + x * 2)
+          .write(TextScreenEntry::from_id_bank(id, palbank));
+        block
+          .index(row_start + x * 2 + 1)
+          .write(TextScreenEntry::from_id_bank(blank, palbank));
+      }
+    }
+  }
+}
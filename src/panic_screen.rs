@@ -0,0 +1,116 @@
+//! A panic handler that shows what went wrong.
+
+use core::{fmt::Write, panic::PanicInfo};
+
+use voladdress::{Safe, VolAddress, VolBlock};
+
+use crate::{
+  gba::{
+    decompress_cp437_data_to, halt_forever, BgCharblock, Color, DebugLevel,
+    DebugLog, DisplayControl, PalRam, TextScreenSize, TextScreenblock,
+    VramPlan, DISPCNT, IE, IME,
+  },
+  TextConsole,
+};
+
+/// How many words of the stack the panic screen shows.
+const STACK_WORDS: usize = 8;
+
+const PANIC_PLAN: VramPlan =
+  VramPlan::new().with_tiles(BgCharblock::_0, 256, false).with_text_bg(
+    0,
+    BgCharblock::_0,
+    TextScreenblock::_31,
+    TextScreenSize::Tiles32x32,
+  );
+
+/// Shows a panic on the screen, and never returns.
+///
+/// Call this from your `#[panic_handler]`:
+///
+/// ```ignore
+/// #[panic_handler]
+/// fn panic_handler(info: &core::panic::PanicInfo) -> ! {
+///   zygravan::panic_screen(info)
+/// }
+/// ```
+///
+/// Interrupts are turned off, and then the display is reset to just BG0 with
+/// the CP437 font in charblock 0 and the map in screenblock 31, so it works
+/// no matter what the program was doing. The screen shows:
+/// * The panic message and where it happened.
+/// * `IME`, `IE`, and `IF` from when the panic started.
+/// * The stack pointer and the first few words of the stack.
+///
+/// The same text is also sent to the emulator's log with a [`DebugLog`], as
+/// `Error` messages. Then, once the screen is drawn, one `Fatal` message is
+/// sent, which mGBA treats as a crash and stops.
+pub fn panic_screen(info: &PanicInfo) -> ! {
+  const IF: VolAddress<u16, Safe, Safe> =
+    unsafe { VolAddress::new(0x0400_0202) };
+  let ime = IME.read();
+  IME.write(false);
+  let ie = IE.read();
+  let if_bits = IF.read();
+  let sp = stack_pointer();
+  // Safety: the stack pointer is in IWRAM, and this stays inside IWRAM.
+  let stack: VolBlock<u32, Safe, (), STACK_WORDS> =
+    unsafe { VolBlock::new(sp & !3) };
+  let mut words = [0_u32; STACK_WORDS];
+  for (i, word) in words.iter_mut().enumerate() {
+    *word = stack.index(i).read();
+  }
+
+  DISPCNT.write(DisplayControl::new().with_forced_blank(true));
+  decompress_cp437_data_to(BgCharblock::_0.tiles4());
+  PalRam::backdrop().write(Color::from_rgb(10, 0, 0));
+  PalRam::bg_palbank(3).index(1).write(Color::YELLOW);
+  PalRam::bg_palbank(7).index(1).write(Color::WHITE);
+  let bg = PANIC_PLAN.background(0);
+  bg.write_control();
+  bg.scroll_to(0, 0);
+
+  let mut console =
+    TextConsole::<30, 20>::new(PANIC_PLAN.screenblock(0)).with_palbank(7);
+  let mut log = DebugLog::new().with_level(DebugLevel::Error);
+  let _ = writeln!(console, "\x1B[33mPANIC\x1B[0m");
+  let _ = writeln!(log, "PANIC");
+  let mut out = Tee(&mut console, &mut log);
+  let _ = writeln!(out, "{}", info);
+  let _ = writeln!(out);
+  let _ = writeln!(out, "IME {} IE {:04X} IF {:04X}", ime as u8, ie.0, if_bits);
+  let _ = writeln!(out, "SP  {:08X}", sp);
+  for (i, pair) in words.chunks(2).enumerate() {
+    let _ = writeln!(out, "+{:02X} {:08X} {:08X}", i * 8, pair[0], pair[1]);
+  }
+
+  console.flush();
+  DISPCNT.write(DisplayControl::new().with_display_bg0(true));
+  // this can stop the emulator, so it goes last.
+  log.set_level(DebugLevel::Fatal);
+  let _ = writeln!(log, "PANIC (details above)");
+  halt_forever()
+}
+
+/// The stack pointer, at about the time this is called.
+#[inline(always)]
+fn stack_pointer() -> usize {
+  let sp: usize;
+  unsafe {
+    asm!(
+      "mov {}, sp",
+      out(reg) sp,
+      options(nomem, nostack, preserves_flags),
+    )
+  };
+  sp
+}
+
+/// Writes to two places at once.
+struct Tee<'a, A, B>(&'a mut A, &'a mut B);
+impl<A: Write, B: Write> Write for Tee<'_, A, B> {
+  fn write_str(&mut self, s: &str) -> core::fmt::Result {
+    self.0.write_str(s)?;
+    self.1.write_str(s)
+  }
+}
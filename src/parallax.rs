@@ -0,0 +1,193 @@
+//! Scrolling several backgrounds at different speeds.
+
+use crate::{
+  gba::{Background, DisplayControl},
+  Fx,
+};
+
+/// One background layer of a [`ParallaxScene`].
+///
+/// The layer's scroll position is `camera * factor + offset`. A factor of
+/// 1.0 moves with the camera, smaller factors look further away, and a factor
+/// of 0.0 keeps the layer fixed on screen (good for UI). The offset moves by
+/// the velocity each [`tick`](ParallaxScene::tick), for layers that drift on
+/// their own (clouds, water, etc).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParallaxLayer {
+  bg: Background,
+  factor_x: Fx<i32, 8>,
+  factor_y: Fx<i32, 8>,
+  offset_x: Fx<i32, 8>,
+  offset_y: Fx<i32, 8>,
+  velocity_x: Fx<i32, 8>,
+  velocity_y: Fx<i32, 8>,
+}
+impl ParallaxLayer {
+  /// A layer that moves with the camera and doesn't drift.
+  #[inline]
+  #[must_use]
+  pub const fn new(bg: Background) -> Self {
+    Self {
+      bg,
+      factor_x: Fx::<i32, 8>::new(1),
+      factor_y: Fx::<i32, 8>::new(1),
+      offset_x: Fx::<i32, 8>::new(0),
+      offset_y: Fx::<i32, 8>::new(0),
+      velocity_x: Fx::<i32, 8>::new(0),
+      velocity_y: Fx::<i32, 8>::new(0),
+    }
+  }
+  #[inline]
+  #[must_use]
+  pub const fn with_factor(self, x: Fx<i32, 8>, y: Fx<i32, 8>) -> Self {
+    Self { factor_x: x, factor_y: y, ..self }
+  }
+  #[inline]
+  #[must_use]
+  pub const fn with_offset(self, x: Fx<i32, 8>, y: Fx<i32, 8>) -> Self {
+    Self { offset_x: x, offset_y: y, ..self }
+  }
+  #[inline]
+  #[must_use]
+  pub const fn with_velocity(self, x: Fx<i32, 8>, y: Fx<i32, 8>) -> Self {
+    Self { velocity_x: x, velocity_y: y, ..self }
+  }
+  /// Sets the layer's priority, 0 is drawn on top of 3.
+  #[inline]
+  #[must_use]
+  pub const fn with_priority(self, z: u16) -> Self {
+    Self { bg: self.bg.with_z_index(z), ..self }
+  }
+  #[inline]
+  #[must_use]
+  pub const fn background(&self) -> Background {
+    self.bg
+  }
+
+  /// The scroll position of this layer for a given camera position.
+  #[inline]
+  #[must_use]
+  pub const fn scroll(
+    &self, camera_x: Fx<i32, 8>, camera_y: Fx<i32, 8>,
+  ) -> (u16, u16) {
+    let x = scale(camera_x, self.factor_x).wrapping_add(self.offset_x);
+    let y = scale(camera_y, self.factor_y).wrapping_add(self.offset_y);
+    (x.to_int() as u16, y.to_int() as u16)
+  }
+}
+
+/// Up to four text backgrounds, all scrolled by a shared camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParallaxScene {
+  layers: [Option<ParallaxLayer>; 4],
+  camera_x: Fx<i32, 8>,
+  camera_y: Fx<i32, 8>,
+}
+impl ParallaxScene {
+  /// A scene with no layers and the camera at `(0, 0)`.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      layers: [None; 4],
+      camera_x: Fx::<i32, 8>::new(0),
+      camera_y: Fx::<i32, 8>::new(0),
+    }
+  }
+
+  /// Puts a layer into the scene, replacing any layer that uses the same
+  /// background.
+  #[inline]
+  pub fn set_layer(&mut self, layer: ParallaxLayer) {
+    self.layers[layer.bg.index()] = Some(layer);
+  }
+  /// Takes a layer out of the scene.
+  #[inline]
+  pub fn remove_layer(&mut self, index: usize) -> Option<ParallaxLayer> {
+    self.layers.get_mut(index).and_then(Option::take)
+  }
+  #[inline]
+  #[must_use]
+  pub fn layer(&self, index: usize) -> Option<&ParallaxLayer> {
+    self.layers.get(index).and_then(Option::as_ref)
+  }
+  #[inline]
+  #[must_use]
+  pub fn layer_mut(&mut self, index: usize) -> Option<&mut ParallaxLayer> {
+    self.layers.get_mut(index).and_then(Option::as_mut)
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn camera(&self) -> (Fx<i32, 8>, Fx<i32, 8>) {
+    (self.camera_x, self.camera_y)
+  }
+  #[inline]
+  pub fn set_camera(&mut self, x: Fx<i32, 8>, y: Fx<i32, 8>) {
+    self.camera_x = x;
+    self.camera_y = y;
+  }
+  #[inline]
+  pub fn move_camera(&mut self, dx: Fx<i32, 8>, dy: Fx<i32, 8>) {
+    self.camera_x = self.camera_x.wrapping_add(dx);
+    self.camera_y = self.camera_y.wrapping_add(dy);
+  }
+
+  /// Advances each layer's offset by its velocity.
+  #[inline]
+  pub fn tick(&mut self) {
+    for layer in self.layers.iter_mut().flatten() {
+      layer.offset_x = layer.offset_x.wrapping_add(layer.velocity_x);
+      layer.offset_y = layer.offset_y.wrapping_add(layer.velocity_y);
+    }
+  }
+
+  /// Adds this scene's layers to the display control value.
+  ///
+  /// Layers that aren't part of the scene are left as they were.
+  #[inline]
+  #[must_use]
+  pub const fn display_control(
+    &self, dispcnt: DisplayControl,
+  ) -> DisplayControl {
+    dispcnt
+      .with_display_bg0(dispcnt.display_bg0() || self.layers[0].is_some())
+      .with_display_bg1(dispcnt.display_bg1() || self.layers[1].is_some())
+      .with_display_bg2(dispcnt.display_bg2() || self.layers[2].is_some())
+      .with_display_bg3(dispcnt.display_bg3() || self.layers[3].is_some())
+  }
+
+  /// Writes each layer's control register.
+  #[inline]
+  pub fn write_controls(&self) {
+    for layer in self.layers.iter().flatten() {
+      layer.bg.write_control();
+    }
+  }
+
+  /// Writes each layer's scroll registers for the current camera position.
+  ///
+  /// Call this during VBlank.
+  #[inline]
+  pub fn write_scrolls(&self) {
+    for layer in self.layers.iter().flatten() {
+      let (x, y) = layer.scroll(self.camera_x, self.camera_y);
+      layer.bg.scroll_to(x, y);
+    }
+  }
+}
+impl Default for ParallaxScene {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// `camera * factor`, done at 64 bits so that big camera positions don't
+/// overflow.
+#[inline]
+#[must_use]
+const fn scale(camera: Fx<i32, 8>, factor: Fx<i32, 8>) -> Fx<i32, 8> {
+  let wide = (camera.to_bits() as i64) * (factor.to_bits() as i64);
+  Fx::<i32, 8>::from_bits((wide >> 8) as i32)
+}
@@ -0,0 +1,116 @@
+//! Lines of text history, kept in EWRAM.
+
+use bytemuck::{cast_slice, cast_slice_mut};
+
+use crate::Ewram;
+
+/// How many lines a [`Scrollback`] holds before the oldest are dropped.
+pub const SCROLLBACK_LINES: usize = 1024;
+
+/// Bytes per line: 32 characters, then 32 palbanks.
+const LINE_BYTES: usize = 64;
+
+/// A ring buffer of old console lines, stored in the first 64K of the
+/// [`Ewram`] token's memory.
+///
+/// Each line holds up to 32 characters along with the palbank of each
+/// character. Holding the [`Ewram`] token means nothing else can use EWRAM
+/// while the scrollback exists, but you can get the token back with
+/// [`into_ewram`](Self::into_ewram).
+#[derive(Debug)]
+pub struct Scrollback {
+  ewram: Ewram,
+  start: usize,
+  len: usize,
+}
+impl Scrollback {
+  /// Makes an empty scrollback.
+  ///
+  /// ## Panics
+  /// * If the token covers less than the 64K that the lines need, which happens
+  ///   when the EWRAM statics use more than 192K.
+  #[inline]
+  #[must_use]
+  pub fn new(ewram: Ewram) -> Self {
+    assert!(
+      ewram.len() * 4 >= SCROLLBACK_LINES * LINE_BYTES,
+      "a scrollback needs 64K of free EWRAM"
+    );
+    Self { ewram, start: 0, len: 0 }
+  }
+
+  /// Gives back the EWRAM token, losing all stored lines.
+  #[inline]
+  #[must_use]
+  pub fn into_ewram(self) -> Ewram {
+    self.ewram
+  }
+
+  /// How many lines are stored.
+  #[inline]
+  #[must_use]
+  pub const fn len(&self) -> usize {
+    self.len
+  }
+  #[inline]
+  #[must_use]
+  pub const fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Forgets all stored lines.
+  #[inline]
+  pub fn clear(&mut self) {
+    self.start = 0;
+    self.len = 0;
+  }
+
+  /// Adds a line, dropping the oldest line if the scrollback is full.
+  ///
+  /// Only the first 32 characters (and palbanks) are kept, and shorter lines
+  /// are padded with spaces.
+  pub fn push_line(&mut self, chars: &[u8], banks: &[u8]) {
+    let slot = if self.len < SCROLLBACK_LINES {
+      self.len += 1;
+      (self.start + self.len - 1) % SCROLLBACK_LINES
+    } else {
+      let slot = self.start;
+      self.start = (self.start + 1) % SCROLLBACK_LINES;
+      slot
+    };
+    let line = self.slot_mut(slot);
+    let (line_chars, line_banks) = line.split_at_mut(32);
+    line_chars.fill(b' ');
+    line_banks.fill(0);
+    let n = chars.len().min(32);
+    line_chars[..n].copy_from_slice(&chars[..n]);
+    let n = banks.len().min(32);
+    line_banks[..n].copy_from_slice(&banks[..n]);
+  }
+
+  /// Gets a line as `(chars, palbanks)`, where line 0 is the oldest.
+  #[inline]
+  #[must_use]
+  pub fn line(&self, i: usize) -> Option<(&[u8], &[u8])> {
+    if i < self.len {
+      let line = self.slot((self.start + i) % SCROLLBACK_LINES);
+      Some(line.split_at(32))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[must_use]
+  fn slot(&self, slot: usize) -> &[u8] {
+    let bytes: &[u8] = cast_slice(&self.ewram[..]);
+    &bytes[slot * LINE_BYTES..][..LINE_BYTES]
+  }
+
+  #[inline]
+  #[must_use]
+  fn slot_mut(&mut self, slot: usize) -> &mut [u8] {
+    let bytes: &mut [u8] = cast_slice_mut(&mut self.ewram[..]);
+    &mut bytes[slot * LINE_BYTES..][..LINE_BYTES]
+  }
+}
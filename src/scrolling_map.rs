@@ -0,0 +1,178 @@
+//! Maps that are bigger than a screenblock.
+
+use crate::gba::{Background, TextScreenEntry, TextScreenblock};
+
+/// How many tile columns can be seen at once (30, +1 for a partial scroll).
+const VIEW_TILES_WIDE: i32 = 31;
+/// How many tile rows can be seen at once (20, +1 for a partial scroll).
+const VIEW_TILES_TALL: i32 = 21;
+
+/// A map of any size, streamed into a single 32x32 screenblock.
+///
+/// The screenblock is used as a ring buffer: map tile `(x, y)` always goes in
+/// screenblock slot `(x % 32, y % 32)`, which is the same wrapping that the
+/// display does when scrolling a 256x256 background. This means the scroll
+/// registers can just be the camera position, and scrolling can continue
+/// forever. The map itself also wraps at its edges.
+///
+/// Each [`update`](Self::update) writes at most one column and one row of the
+/// map into VRAM, so that it fits in VBlank. If the camera moves more than 8
+/// pixels in a frame the loaded tiles will lag behind for a few frames, so
+/// for big jumps use [`reload`](Self::reload) while the display is blanked.
+#[derive(Debug, Clone, Copy)]
+pub struct ScrollingMap {
+  map: &'static [TextScreenEntry],
+  width: usize,
+  height: usize,
+  screenblock: TextScreenblock,
+  camera_x: i32,
+  camera_y: i32,
+  loaded_x: i32,
+  loaded_y: i32,
+}
+impl ScrollingMap {
+  /// Makes a new scrolling map, with the camera at `(0, 0)`.
+  ///
+  /// The map is given in row-major order, `width` tiles per row. Nothing is
+  /// written to VRAM until you call [`reload`](Self::reload).
+  ///
+  /// ## Panics
+  /// * If the map is empty or isn't a whole number of rows.
+  #[inline]
+  #[must_use]
+  pub const fn new(
+    map: &'static [TextScreenEntry], width: usize, screenblock: TextScreenblock,
+  ) -> Self {
+    if width == 0 || map.is_empty() || !map.len().is_multiple_of(width) {
+      panic!("map must be a non-zero whole number of rows");
+    }
+    Self {
+      map,
+      width,
+      height: map.len() / width,
+      screenblock,
+      camera_x: 0,
+      camera_y: 0,
+      loaded_x: 0,
+      loaded_y: 0,
+    }
+  }
+
+  /// Width of the map, in tiles.
+  #[inline]
+  #[must_use]
+  pub const fn width(&self) -> usize {
+    self.width
+  }
+  /// Height of the map, in tiles.
+  #[inline]
+  #[must_use]
+  pub const fn height(&self) -> usize {
+    self.height
+  }
+  /// The camera position, in pixels.
+  #[inline]
+  #[must_use]
+  pub const fn camera(&self) -> (i32, i32) {
+    (self.camera_x, self.camera_y)
+  }
+  /// Moves the camera to a new position, in pixels.
+  ///
+  /// This doesn't touch VRAM, call [`update`](Self::update) to do that.
+  #[inline]
+  pub fn set_camera(&mut self, x: i32, y: i32) {
+    self.camera_x = x;
+    self.camera_y = y;
+  }
+  /// Moves the camera by a delta, in pixels.
+  #[inline]
+  pub fn move_camera(&mut self, dx: i32, dy: i32) {
+    self.set_camera(self.camera_x + dx, self.camera_y + dy);
+  }
+
+  /// The values to put in the background's scroll registers.
+  #[inline]
+  #[must_use]
+  pub const fn scroll(&self) -> (u16, u16) {
+    (self.camera_x as u16, self.camera_y as u16)
+  }
+  /// Sets the scroll registers of a background to match the camera.
+  #[inline]
+  pub fn scroll_background(&self, bg: Background) {
+    let (x, y) = self.scroll();
+    bg.scroll_to(x, y);
+  }
+
+  /// If the tiles in VRAM cover everything the camera can see.
+  #[inline]
+  #[must_use]
+  pub const fn is_caught_up(&self) -> bool {
+    self.loaded_x == self.camera_x.div_euclid(8)
+      && self.loaded_y == self.camera_y.div_euclid(8)
+  }
+
+  /// Streams newly visible map tiles into VRAM.
+  ///
+  /// This moves the loaded area at most one column and one row closer to the
+  /// camera, so call it once per frame during VBlank.
+  pub fn update(&mut self) {
+    let target_x = self.camera_x.div_euclid(8);
+    let target_y = self.camera_y.div_euclid(8);
+    if target_x > self.loaded_x {
+      self.load_column(self.loaded_x + VIEW_TILES_WIDE);
+      self.loaded_x += 1;
+    } else if target_x < self.loaded_x {
+      self.loaded_x -= 1;
+      self.load_column(self.loaded_x);
+    }
+    if target_y > self.loaded_y {
+      self.load_row(self.loaded_y + VIEW_TILES_TALL);
+      self.loaded_y += 1;
+    } else if target_y < self.loaded_y {
+      self.loaded_y -= 1;
+      self.load_row(self.loaded_y);
+    }
+  }
+
+  /// Loads every visible tile at the current camera position.
+  ///
+  /// This is 651 writes, which is too many to do in VBlank along with other
+  /// work. Use it for the first frame, or after a big camera jump, while the
+  /// display is blanked.
+  pub fn reload(&mut self) {
+    self.loaded_x = self.camera_x.div_euclid(8);
+    self.loaded_y = self.camera_y.div_euclid(8);
+    for y in self.loaded_y..(self.loaded_y + VIEW_TILES_TALL) {
+      self.load_row(y);
+    }
+  }
+
+  /// The map entry at a tile position, wrapping around the map's edges.
+  #[inline]
+  #[must_use]
+  pub fn entry(&self, x: i32, y: i32) -> TextScreenEntry {
+    let x = x.rem_euclid(self.width as i32) as usize;
+    let y = y.rem_euclid(self.height as i32) as usize;
+    self.map[y * self.width + x]
+  }
+
+  #[inline]
+  fn load_tile(&self, x: i32, y: i32) {
+    let slot = ((y & 31) * 32 + (x & 31)) as usize;
+    self.screenblock.as_volblock().index(slot).write(self.entry(x, y));
+  }
+
+  #[inline]
+  fn load_column(&self, x: i32) {
+    for y in self.loaded_y..(self.loaded_y + VIEW_TILES_TALL) {
+      self.load_tile(x, y);
+    }
+  }
+
+  #[inline]
+  fn load_row(&self, y: i32) {
+    for x in self.loaded_x..(self.loaded_x + VIEW_TILES_WIDE) {
+      self.load_tile(x, y);
+    }
+  }
+}
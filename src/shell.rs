@@ -0,0 +1,483 @@
+//! A tiny command shell, for using a text console as a debug console.
+
+use core::fmt::{self, Write};
+
+#[cfg(target_arch = "arm")]
+use crate::gba::SectionSizes;
+
+/// The most arguments (including the command name) a line can have.
+pub const MAX_ARGS: usize = 8;
+
+/// The function that runs a command.
+///
+/// `args[0]` is the command's own name.
+pub type CommandFn =
+  fn(args: &[&str], out: &mut dyn Write) -> Result<(), CommandError>;
+
+/// Something that went wrong running a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandError {
+  /// A quoted argument didn't have a closing quote.
+  UnterminatedQuote,
+  /// The line had more than [`MAX_ARGS`] arguments.
+  TooManyArgs,
+  /// No command with that name is registered.
+  UnknownCommand,
+  /// The command didn't like its arguments.
+  BadArguments,
+  /// Writing the output failed.
+  Write,
+}
+impl From<fmt::Error> for CommandError {
+  #[inline]
+  fn from(_: fmt::Error) -> Self {
+    Self::Write
+  }
+}
+impl fmt::Display for CommandError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Self::UnterminatedQuote => "unterminated quote",
+      Self::TooManyArgs => "too many arguments",
+      Self::UnknownCommand => "unknown command",
+      Self::BadArguments => "bad arguments",
+      Self::Write => "write failed",
+    })
+  }
+}
+
+/// A named command that the shell can run.
+#[derive(Debug, Clone, Copy)]
+pub struct Command {
+  pub name: &'static str,
+  /// What goes after the name, shown by `help` and on bad arguments.
+  pub usage: &'static str,
+  pub run: CommandFn,
+}
+
+/// Splits a line into whitespace separated arguments.
+///
+/// An argument can be wrapped in `"` or `'` to include spaces in it. There's
+/// no escaping, so a quote can only appear inside the other kind of quote.
+///
+/// Returns how many of `args` were filled in.
+pub fn tokenize<'a>(
+  line: &'a str, args: &mut [&'a str],
+) -> Result<usize, CommandError> {
+  let bytes = line.as_bytes();
+  let mut count = 0;
+  let mut i = 0;
+  loop {
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+      i += 1;
+    }
+    if i == bytes.len() {
+      return Ok(count);
+    }
+    let (start, end, next) = match bytes[i] {
+      quote @ (b'"' | b'\'') => {
+        let start = i + 1;
+        match bytes[start..].iter().position(|&b| b == quote) {
+          Some(n) => (start, start + n, start + n + 1),
+          None => return Err(CommandError::UnterminatedQuote),
+        }
+      }
+      _ => {
+        let n = bytes[i..]
+          .iter()
+          .position(|b| b.is_ascii_whitespace())
+          .unwrap_or(bytes.len() - i);
+        (i, i + n, i + n)
+      }
+    };
+    if count == args.len() {
+      return Err(CommandError::TooManyArgs);
+    }
+    args[count] = &line[start..end];
+    count += 1;
+    i = next;
+  }
+}
+
+/// Parses a number written in decimal, or in hex with a `0x` prefix.
+///
+/// Underscores are allowed as separators, so `0x0400_0000` works.
+#[must_use]
+pub fn parse_number(s: &str) -> Option<u32> {
+  let (digits, radix) = match s.strip_prefix("0x").or(s.strip_prefix("0X")) {
+    Some(hex) => (hex, 16),
+    None => (s, 10),
+  };
+  let mut any_digit = false;
+  let mut value = 0_u32;
+  for ch in digits.chars() {
+    if ch == '_' {
+      continue;
+    }
+    let digit = ch.to_digit(radix)?;
+    value = value.checked_mul(radix)?.checked_add(digit)?;
+    any_digit = true;
+  }
+  if any_digit {
+    Some(value)
+  } else {
+    None
+  }
+}
+
+/// A set of up to `N` commands, and a way to run lines of text against them.
+///
+/// `help` is always available and lists the registered commands. The other
+/// built in commands are added with [`with_builtins`](Self::with_builtins):
+///
+/// * `clear`: clears the console.
+/// * `peek <addr> [8|16|32]`: reads memory (16 bits by default).
+/// * `poke <addr> <val> [8|16|32]`: writes memory (16 bits by default).
+/// * `regs`: shows the display and interrupt registers.
+/// * `mem`: shows how much IWRAM and EWRAM the linker used (GBA builds only).
+#[derive(Debug, Clone, Copy)]
+pub struct CommandRegistry<const N: usize> {
+  commands: [Option<Command>; N],
+}
+impl<const N: usize> CommandRegistry<N> {
+  /// Makes a registry with no commands.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self { commands: [None; N] }
+  }
+
+  /// Adds the built in commands.
+  ///
+  /// ## Panics
+  /// * If there isn't room for them.
+  #[inline]
+  #[must_use]
+  pub fn with_builtins(self) -> Self {
+    let registry = self
+      .with_command(Command { name: "clear", usage: "", run: cmd_clear })
+      .with_command(Command {
+        name: "peek",
+        usage: "<addr> [8|16|32]",
+        run: cmd_peek,
+      })
+      .with_command(Command {
+        name: "poke",
+        usage: "<addr> <val> [8|16|32]",
+        run: cmd_poke,
+      })
+      .with_command(Command { name: "regs", usage: "", run: cmd_regs });
+    #[cfg(target_arch = "arm")]
+    let registry =
+      registry.with_command(Command { name: "mem", usage: "", run: cmd_mem });
+    registry
+  }
+
+  /// Adds a command.
+  ///
+  /// ## Panics
+  /// * If the registry is full, or the name is already used.
+  #[inline]
+  #[must_use]
+  pub fn with_command(mut self, command: Command) -> Self {
+    assert!(self.register(command), "could not register command");
+    self
+  }
+
+  /// Adds a command.
+  ///
+  /// ## Failure
+  /// * If the registry is full, or the name is already used.
+  pub fn register(&mut self, command: Command) -> bool {
+    if command.name == "help" || self.find(command.name).is_some() {
+      return false;
+    }
+    match self.commands.iter_mut().find(|slot| slot.is_none()) {
+      Some(slot) => {
+        *slot = Some(command);
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Finds a command by name.
+  #[inline]
+  #[must_use]
+  pub fn find(&self, name: &str) -> Option<&Command> {
+    self.commands.iter().flatten().find(|command| command.name == name)
+  }
+
+  /// Runs a line, giving back any error.
+  ///
+  /// A blank line does nothing.
+  pub fn execute(
+    &self, line: &str, out: &mut dyn Write,
+  ) -> Result<(), CommandError> {
+    let mut args = [""; MAX_ARGS];
+    let count = tokenize(line, &mut args)?;
+    let args = &args[..count];
+    match args.first().copied() {
+      None => Ok(()),
+      Some("help") => self.help(out),
+      Some(name) => {
+        let command = self.find(name).ok_or(CommandError::UnknownCommand)?;
+        (command.run)(args, out)
+      }
+    }
+  }
+
+  /// Runs a line, writing any error to the output.
+  pub fn run_line(&self, line: &str, out: &mut dyn Write) -> fmt::Result {
+    match self.execute(line, out) {
+      Ok(()) => Ok(()),
+      Err(CommandError::Write) => Err(fmt::Error),
+      Err(CommandError::BadArguments) => {
+        let mut args = [""; 1];
+        let _ = tokenize(line, &mut args);
+        match self.find(args[0]) {
+          Some(command) => {
+            writeln!(out, "usage: {} {}", command.name, command.usage)
+          }
+          None => writeln!(out, "{}", CommandError::BadArguments),
+        }
+      }
+      Err(e) => writeln!(out, "{}", e),
+    }
+  }
+
+  fn help(&self, out: &mut dyn Write) -> Result<(), CommandError> {
+    writeln!(out, "help")?;
+    for command in self.commands.iter().flatten() {
+      writeln!(out, "{} {}", command.name, command.usage)?;
+    }
+    Ok(())
+  }
+}
+impl<const N: usize> Default for CommandRegistry<N> {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+fn cmd_clear(_args: &[&str], out: &mut dyn Write) -> Result<(), CommandError> {
+  write!(out, "\x1B[2J\x1B[H")?;
+  Ok(())
+}
+
+/// Parses the address and optional width argument used by peek and poke.
+fn address_and_width(
+  addr: &str, width: Option<&str>,
+) -> Result<(usize, u32), CommandError> {
+  let addr = parse_number(addr).ok_or(CommandError::BadArguments)? as usize;
+  let bits = match width {
+    None => 16,
+    Some(w) => parse_number(w).ok_or(CommandError::BadArguments)?,
+  };
+  let align = match bits {
+    8 => 1,
+    16 => 2,
+    32 => 4,
+    _ => return Err(CommandError::BadArguments),
+  };
+  if !addr.is_multiple_of(align) {
+    return Err(CommandError::BadArguments);
+  }
+  Ok((addr, bits))
+}
+
+fn cmd_peek(args: &[&str], out: &mut dyn Write) -> Result<(), CommandError> {
+  let (addr, bits) = match args {
+    [_, addr] => address_and_width(addr, None)?,
+    [_, addr, width] => address_and_width(addr, Some(width))?,
+    _ => return Err(CommandError::BadArguments),
+  };
+  // Safety: this is a debug tool, it reads whatever it's told to.
+  let value = unsafe {
+    match bits {
+      8 => (addr as *const u8).read_volatile() as u32,
+      16 => (addr as *const u16).read_volatile() as u32,
+      _ => (addr as *const u32).read_volatile(),
+    }
+  };
+  let digits = bits as usize / 4;
+  writeln!(out, "{:08X}: {:0digits$X}", addr, value, digits = digits)?;
+  Ok(())
+}
+
+fn cmd_poke(args: &[&str], out: &mut dyn Write) -> Result<(), CommandError> {
+  let (addr, value, bits) = match args {
+    [_, addr, value] => {
+      let (addr, bits) = address_and_width(addr, None)?;
+      (addr, value, bits)
+    }
+    [_, addr, value, width] => {
+      let (addr, bits) = address_and_width(addr, Some(width))?;
+      (addr, value, bits)
+    }
+    _ => return Err(CommandError::BadArguments),
+  };
+  let value = parse_number(value).ok_or(CommandError::BadArguments)?;
+  if bits < 32 && value >> bits != 0 {
+    return Err(CommandError::BadArguments);
+  }
+  // Safety: this is a debug tool, it writes whatever it's told to.
+  unsafe {
+    match bits {
+      8 => (addr as *mut u8).write_volatile(value as u8),
+      16 => (addr as *mut u16).write_volatile(value as u16),
+      _ => (addr as *mut u32).write_volatile(value),
+    }
+  }
+  let digits = bits as usize / 4;
+  writeln!(out, "{:08X} <- {:0digits$X}", addr, value, digits = digits)?;
+  Ok(())
+}
+
+fn cmd_regs(_args: &[&str], out: &mut dyn Write) -> Result<(), CommandError> {
+  const REGS: [(&str, usize); 5] = [
+    ("DISPCNT ", 0x0400_0000),
+    ("DISPSTAT", 0x0400_0004),
+    ("IE      ", 0x0400_0200),
+    ("IF      ", 0x0400_0202),
+    ("IME     ", 0x0400_0208),
+  ];
+  for (name, addr) in REGS {
+    // Safety: these are all readable 16-bit IO registers.
+    let value = unsafe { (addr as *const u16).read_volatile() };
+    writeln!(out, "{} {:04X}", name, value)?;
+  }
+  Ok(())
+}
+
+#[cfg(target_arch = "arm")]
+fn cmd_mem(_args: &[&str], out: &mut dyn Write) -> Result<(), CommandError> {
+  let sizes = SectionSizes::get();
+  writeln!(
+    out,
+    "IWRAM {:5}/{} ({}+{})",
+    sizes.iwram_used(),
+    SectionSizes::IWRAM_SIZE,
+    sizes.iwram_data,
+    sizes.iwram_bss
+  )?;
+  writeln!(
+    out,
+    "EWRAM {:6}/{} ({}+{})",
+    sizes.ewram_used(),
+    SectionSizes::EWRAM_SIZE,
+    sizes.ewram_data,
+    sizes.ewram_bss
+  )?;
+  Ok(())
+}
+
+#[cfg(all(test, not(target_arch = "arm")))]
+mod tests {
+  extern crate std;
+
+  use super::*;
+  use quickcheck::quickcheck;
+  use std::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+  };
+
+  fn tokens(line: &str) -> Result<Vec<&str>, CommandError> {
+    let mut args = [""; MAX_ARGS];
+    let count = tokenize(line, &mut args)?;
+    Ok(args[..count].to_vec())
+  }
+
+  #[test]
+  fn tokenize_splits_on_whitespace() {
+    assert_eq!(
+      tokens("peek  0x0400_0000\t2 "),
+      Ok(vec!["peek", "0x0400_0000", "2"])
+    );
+    assert_eq!(tokens(""), Ok(vec![]));
+    assert_eq!(tokens(" \t "), Ok(vec![]));
+  }
+
+  #[test]
+  fn tokenize_quotes() {
+    assert_eq!(
+      tokens(r#"echo "a b" 'say "hi"' """#),
+      Ok(vec!["echo", "a b", r#"say "hi""#, ""])
+    );
+    assert_eq!(tokens("echo \"a b"), Err(CommandError::UnterminatedQuote));
+  }
+
+  #[test]
+  fn tokenize_limits_args() {
+    assert_eq!(tokens("1 2 3 4 5 6 7 8").map(|args| args.len()), Ok(MAX_ARGS));
+    assert_eq!(tokens("1 2 3 4 5 6 7 8 9"), Err(CommandError::TooManyArgs));
+  }
+
+  #[test]
+  fn parse_number_formats() {
+    assert_eq!(parse_number("1234"), Some(1234));
+    assert_eq!(parse_number("0x1f"), Some(0x1F));
+    assert_eq!(parse_number("0X1F"), Some(0x1F));
+    assert_eq!(parse_number("0x0400_0000"), Some(0x0400_0000));
+    assert_eq!(parse_number("1_000"), Some(1000));
+    assert_eq!(parse_number("4294967295"), Some(u32::MAX));
+  }
+
+  #[test]
+  fn parse_number_rejects() {
+    assert_eq!(parse_number(""), None);
+    assert_eq!(parse_number("0x"), None);
+    assert_eq!(parse_number("_"), None);
+    assert_eq!(parse_number("12a"), None);
+    assert_eq!(parse_number("-1"), None);
+    assert_eq!(parse_number("4294967296"), None);
+    assert_eq!(parse_number("0x1_0000_0000"), None);
+  }
+
+  quickcheck! {
+    fn parse_number_round_trips(n: u32) -> bool {
+      parse_number(&n.to_string()) == Some(n)
+        && parse_number(&format!("0x{:X}", n)) == Some(n)
+        && parse_number(&format!("0x{:x}", n)) == Some(n)
+    }
+  }
+
+  fn echo(args: &[&str], out: &mut dyn Write) -> Result<(), CommandError> {
+    if args.len() < 2 {
+      return Err(CommandError::BadArguments);
+    }
+    writeln!(out, "{}", args[1..].join(" "))?;
+    Ok(())
+  }
+
+  const ECHO: Command = Command { name: "echo", usage: "<text>...", run: echo };
+
+  #[test]
+  fn registry_runs_lines() {
+    let registry = CommandRegistry::<2>::new().with_command(ECHO);
+    let mut out = String::new();
+    registry.run_line("echo 'a  b' c", &mut out).unwrap();
+    registry.run_line("", &mut out).unwrap();
+    registry.run_line("echo", &mut out).unwrap();
+    registry.run_line("nope", &mut out).unwrap();
+    registry.run_line("help", &mut out).unwrap();
+    assert_eq!(
+      out,
+      "a  b c\nusage: echo <text>...\nunknown command\nhelp\necho <text>...\n"
+    );
+  }
+
+  #[test]
+  fn registry_rejects_duplicates() {
+    let mut registry = CommandRegistry::<2>::new();
+    assert!(registry.register(ECHO));
+    assert!(!registry.register(ECHO));
+    assert!(!registry.register(Command { name: "help", ..ECHO }));
+    assert!(registry.register(Command { name: "say", ..ECHO }));
+    assert!(!registry.register(Command { name: "shout", ..ECHO }));
+  }
+}
@@ -0,0 +1,429 @@
+//! A queue for passing values between irq handlers and the main loop.
+
+use core::{
+  cell::UnsafeCell,
+  mem::MaybeUninit,
+  sync::atomic::{compiler_fence, Ordering},
+};
+
+use crate::gba::{a32_swpb, GbaCell};
+
+/// A fixed size queue with one side pushing and the other side popping,
+/// without turning interrupts off.
+///
+/// The usual setup is an irq handler pushing events and the main loop popping
+/// them:
+///
+/// ```ignore
+/// static KEY_EVENTS: SpscQueue<Keys, 8> = SpscQueue::new();
+///
+/// fn on_keypad() {
+///   let _ = KEY_EVENTS.push(get_keys());
+/// }
+///
+/// while let Some(keys) = KEY_EVENTS.pop() {
+///   // ...
+/// }
+/// ```
+///
+/// The read and write positions are each only written by one side, and each
+/// is a single instruction read or write (as with [`GbaCell`]), so the two
+/// sides can interrupt each other at any point.
+///
+/// If two pushes (or two pops) overlap, such as main code pushing when an
+/// irq handler that pushes goes off, the one that started second fails
+/// instead of breaking the queue.
+#[derive(Debug)]
+pub struct SpscQueue<T, const N: usize> {
+  slots: UnsafeCell<MaybeUninit<[T; N]>>,
+  /// The position to pop from next, only written by [`pop`](Self::pop).
+  head: GbaCell<u32>,
+  /// The position to push to next, only written by [`push`](Self::push).
+  tail: GbaCell<u32>,
+  pushing: GbaCell<u8>,
+  popping: GbaCell<u8>,
+}
+// Safety: each value is only ever touched by one side at a time.
+unsafe impl<T: Send, const N: usize> Sync for SpscQueue<T, N> {}
+impl<T, const N: usize> SpscQueue<T, N> {
+  /// Makes an empty queue.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    // Safety: `u32` and `u8` are accessed with a single instruction.
+    unsafe {
+      Self {
+        slots: UnsafeCell::new(MaybeUninit::uninit()),
+        head: GbaCell::new(0),
+        tail: GbaCell::new(0),
+        pushing: GbaCell::new(0),
+        popping: GbaCell::new(0),
+      }
+    }
+  }
+
+  /// How many values the queue can hold.
+  #[inline]
+  #[must_use]
+  pub const fn capacity(&self) -> usize {
+    N
+  }
+
+  /// How many values are in the queue.
+  ///
+  /// If the other side is busy this can be out of date by the time you use
+  /// it.
+  #[inline]
+  #[must_use]
+  pub fn len(&self) -> usize {
+    Self::distance(self.head.read(), self.tail.read())
+  }
+
+  /// If the queue has no values in it.
+  #[inline]
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// If the queue has no room for more values.
+  #[inline]
+  #[must_use]
+  pub fn is_full(&self) -> bool {
+    self.len() == N
+  }
+
+  /// Adds a value to the back of the queue.
+  ///
+  /// ## Failure
+  /// * If the queue is full, or another push is already going, the value is
+  ///   given back.
+  pub fn push(&self, value: T) -> Result<(), T> {
+    if unsafe { a32_swpb(1, self.pushing.get_ptr()) } != 0 {
+      return Err(value);
+    }
+    preempt_point();
+    let tail = self.tail.read();
+    preempt_point();
+    let out = if Self::distance(self.head.read(), tail) == N {
+      Err(value)
+    } else {
+      preempt_point();
+      // Safety: the slot is outside of the values the popping side can see,
+      // and no other push is going.
+      unsafe { self.slot(tail).write(value) };
+      // the value has to be written before the popping side can see it.
+      compiler_fence(Ordering::Release);
+      preempt_point();
+      self.tail.write(Self::next(tail));
+      Ok(())
+    };
+    preempt_point();
+    self.pushing.write(0);
+    out
+  }
+
+  /// Takes the value from the front of the queue, if there is one.
+  ///
+  /// This also gives `None` if another pop is already going.
+  pub fn pop(&self) -> Option<T> {
+    if unsafe { a32_swpb(1, self.popping.get_ptr()) } != 0 {
+      return None;
+    }
+    preempt_point();
+    let head = self.head.read();
+    preempt_point();
+    let out = if Self::distance(head, self.tail.read()) == 0 {
+      None
+    } else {
+      // the value has to be read after the push that wrote it is seen.
+      compiler_fence(Ordering::Acquire);
+      preempt_point();
+      // Safety: the slot holds a value the pushing side is done with, and no
+      // other pop is going.
+      let value = unsafe { self.slot(head).read() };
+      compiler_fence(Ordering::Release);
+      preempt_point();
+      self.head.write(Self::next(head));
+      Some(value)
+    };
+    preempt_point();
+    self.popping.write(0);
+    out
+  }
+
+  /// The slot for a position.
+  ///
+  /// Positions go from 0 up to `2*N`, so that a full queue and an empty
+  /// queue look different.
+  #[inline]
+  #[must_use]
+  fn slot(&self, pos: u32) -> *mut T {
+    let i = pos as usize % N;
+    // Safety: `i` is less than `N`, so this is inside the array.
+    unsafe { self.slots.get().cast::<T>().add(i) }
+  }
+
+  #[inline]
+  #[must_use]
+  fn next(pos: u32) -> u32 {
+    if pos as usize + 1 == 2 * N {
+      0
+    } else {
+      pos + 1
+    }
+  }
+
+  #[inline]
+  #[must_use]
+  fn distance(head: u32, tail: u32) -> usize {
+    if tail >= head {
+      (tail - head) as usize
+    } else {
+      tail as usize + 2 * N - head as usize
+    }
+  }
+}
+impl<T, const N: usize> Default for SpscQueue<T, N> {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+impl<T, const N: usize> Drop for SpscQueue<T, N> {
+  fn drop(&mut self) {
+    while self.pop().is_some() {}
+  }
+}
+
+#[cfg(not(all(test, not(target_arch = "arm"))))]
+#[inline(always)]
+fn preempt_point() {}
+#[cfg(all(test, not(target_arch = "arm")))]
+use tests::preempt_point;
+
+/// These use `std`, so they only run on the host.
+#[cfg(all(test, not(target_arch = "arm")))]
+mod tests {
+  //! These model an interrupt going off part way through a push or pop.
+  //!
+  //! Every shared access in `push` and `pop` has a `preempt_point` between
+  //! it and the next one, and a test can set a hook that runs at the n-th
+  //! point, standing in for an irq handler. Each test tries every point.
+
+  extern crate std;
+
+  use super::*;
+  use std::{
+    boxed::Box, cell::RefCell, collections::VecDeque, rc::Rc, vec::Vec,
+  };
+
+  type Hook = Box<dyn FnOnce()>;
+
+  std::thread_local! {
+    static HOOK: RefCell<Option<(usize, Hook)>> = RefCell::new(None);
+  }
+
+  pub(super) fn preempt_point() {
+    let ready = HOOK.with(|hook| {
+      let mut hook = hook.borrow_mut();
+      match hook.as_mut() {
+        Some((0, _)) => hook.take().map(|(_, f)| f),
+        Some((n, _)) => {
+          *n -= 1;
+          None
+        }
+        None => None,
+      }
+    });
+    if let Some(f) = ready {
+      f();
+    }
+  }
+
+  /// Runs `f` at the `n`-th preempt point from now.
+  fn interrupt_at(n: usize, f: impl FnOnce() + 'static) {
+    HOOK.with(|hook| *hook.borrow_mut() = Some((n, Box::new(f))));
+  }
+
+  /// If the hook was used, clearing it either way.
+  fn interrupted() -> bool {
+    HOOK.with(|hook| hook.borrow_mut().take().is_none())
+  }
+
+  fn leak<const N: usize>() -> &'static SpscQueue<u32, N> {
+    Box::leak(Box::new(SpscQueue::new()))
+  }
+
+  fn drain<const N: usize>(q: &SpscQueue<u32, N>) -> Vec<u32> {
+    core::iter::from_fn(|| q.pop()).collect()
+  }
+
+  #[test]
+  fn fifo_order_and_capacity() {
+    let q = leak::<4>();
+    assert!(q.is_empty());
+    for i in 0..4 {
+      assert_eq!(q.push(i), Ok(()));
+    }
+    assert!(q.is_full());
+    assert_eq!(q.push(99), Err(99));
+    assert_eq!(drain(q), [0, 1, 2, 3]);
+    assert_eq!(q.pop(), None);
+  }
+
+  #[test]
+  fn positions_wrap_around() {
+    let q = leak::<3>();
+    let mut next = 0;
+    for round in 0..20 {
+      let n = round % 4;
+      for i in 0..n {
+        assert_eq!(q.push(next + i).is_ok(), i < 3);
+      }
+      let expected: Vec<u32> = (next..next + n.min(3)).collect();
+      assert_eq!(drain(q), expected);
+      next += n;
+    }
+  }
+
+  #[test]
+  fn irq_push_during_pop() {
+    for start_len in 0..=3 {
+      for point in 0..6 {
+        let q = leak::<3>();
+        for i in 0..start_len {
+          q.push(i).unwrap();
+        }
+        let irq_push = Rc::new(RefCell::new(None));
+        let result = irq_push.clone();
+        interrupt_at(point, move || {
+          *result.borrow_mut() = Some(q.push(100));
+        });
+        let popped = q.pop();
+        interrupted();
+        let pushed = irq_push.borrow().is_some_and(|r| r.is_ok());
+
+        // nothing is lost or duplicated, and the order is kept
+        let mut all: Vec<u32> = popped.into_iter().collect();
+        all.extend(drain(q));
+        let mut expected: Vec<u32> = (0..start_len).collect();
+        if pushed {
+          expected.push(100);
+        }
+        assert_eq!(all, expected, "len {start_len} point {point}");
+        // the pop only misses out if the queue really was empty
+        assert!(popped.is_some() || start_len == 0);
+        // and the push only fails if the queue really was full
+        assert!(pushed || irq_push.borrow().is_none() || start_len == 3);
+      }
+    }
+  }
+
+  #[test]
+  fn irq_pop_during_push() {
+    for start_len in 0..=3 {
+      for point in 0..6 {
+        let q = leak::<3>();
+        for i in 0..start_len {
+          q.push(i).unwrap();
+        }
+        let irq_pop = Rc::new(RefCell::new(None));
+        let result = irq_pop.clone();
+        interrupt_at(point, move || {
+          *result.borrow_mut() = Some(q.pop());
+        });
+        let pushed = q.push(100);
+        interrupted();
+
+        let irq_popped = irq_pop.borrow().flatten();
+        let mut all: Vec<u32> = irq_popped.into_iter().collect();
+        all.extend(drain(q));
+        let mut expected: Vec<u32> = (0..start_len).collect();
+        if pushed.is_ok() {
+          expected.push(100);
+        }
+        assert_eq!(all, expected, "len {start_len} point {point}");
+        assert!(pushed.is_ok() || start_len == 3);
+        assert!(
+          irq_popped.is_some() || irq_pop.borrow().is_none() || start_len == 0
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn overlapping_pushes_fail_cleanly() {
+    for point in 0..5 {
+      let q = leak::<4>();
+      let irq_push = Rc::new(RefCell::new(None));
+      let result = irq_push.clone();
+      interrupt_at(point, move || {
+        *result.borrow_mut() = Some(q.push(7));
+      });
+      assert_eq!(q.push(1), Ok(()));
+      assert!(interrupted());
+      assert_eq!(*irq_push.borrow(), Some(Err(7)), "point {point}");
+      assert_eq!(drain(q), [1]);
+    }
+  }
+
+  #[test]
+  fn overlapping_pops_fail_cleanly() {
+    for point in 0..5 {
+      let q = leak::<4>();
+      q.push(1).unwrap();
+      q.push(2).unwrap();
+      let irq_pop = Rc::new(RefCell::new(None));
+      let result = irq_pop.clone();
+      interrupt_at(point, move || {
+        *result.borrow_mut() = Some(q.pop());
+      });
+      assert_eq!(q.pop(), Some(1));
+      assert!(interrupted());
+      assert_eq!(*irq_pop.borrow(), Some(None), "point {point}");
+      assert_eq!(drain(q), [2]);
+    }
+  }
+
+  #[test]
+  fn random_interleavings_match_a_model() {
+    let mut seed = 0x1234_5678_u32;
+    let mut rand = move || {
+      seed ^= seed << 13;
+      seed ^= seed >> 17;
+      seed ^= seed << 5;
+      seed
+    };
+    let q = leak::<5>();
+    let mut model = VecDeque::new();
+    let mut next = 0;
+    for _ in 0..5000 {
+      if rand() % 2 == 0 {
+        let expected = if model.len() < 5 { Ok(()) } else { Err(next) };
+        assert_eq!(q.push(next), expected);
+        if expected.is_ok() {
+          model.push_back(next);
+        }
+        next += 1;
+      } else {
+        assert_eq!(q.pop(), model.pop_front());
+      }
+      assert_eq!(q.len(), model.len());
+    }
+  }
+
+  #[test]
+  fn drop_drops_the_values() {
+    let counter = Rc::new(());
+    {
+      let q: SpscQueue<Rc<()>, 4> = SpscQueue::new();
+      for _ in 0..3 {
+        q.push(counter.clone()).unwrap();
+      }
+      drop(q.pop());
+      assert_eq!(Rc::strong_count(&counter), 3);
+    }
+    assert_eq!(Rc::strong_count(&counter), 1);
+  }
+}
@@ -0,0 +1,136 @@
+//! A test framework for tests that run on the GBA itself.
+//!
+//! `cargo test` builds each test target into a ROM, and the runner in
+//! `.cargo/config.toml` (`tools/gba_runner.py`) runs it in a headless mGBA.
+//! The ROM reports each test to mGBA's log through a [`DebugLog`], and the
+//! runner turns that into the usual pass/fail output.
+//!
+//! A test file in `tests/` is set up like this:
+//!
+//! ```ignore
+//! #![no_std]
+//! #![no_main]
+//! #![feature(custom_test_frameworks)]
+//! #![test_runner(zygravan::testing::test_runner)]
+//! #![reexport_test_harness_main = "test_main"]
+//!
+//! #[no_mangle]
+//! extern "C" fn main() -> ! {
+//!   test_main();
+//!   zygravan::testing::exit(0)
+//! }
+//!
+//! #[panic_handler]
+//! fn panic_handler(info: &core::panic::PanicInfo) -> ! {
+//!   zygravan::testing::test_panic(info)
+//! }
+//!
+//! #[test_case]
+//! fn addition() {
+//!   assert_eq!(1 + 1, 2);
+//! }
+//! ```
+//!
+//! A test fails by panicking, and that ends the whole run, since there's no
+//! unwinding to recover with.
+
+use core::{any::type_name, fmt::Write, panic::PanicInfo};
+
+use crate::gba::{halt_forever, DebugLevel, DebugLog};
+
+/// The software interrupt that ends a test run, with the exit code in `r0`.
+///
+/// This isn't a real BIOS function. `mgba-rom-test -S 0x27` stops when it
+/// sees it.
+pub const EXIT_SWI: usize = 0x27;
+
+/// Something that can be run as a test.
+///
+/// This is done for any `fn()`, using the function's name as the test name.
+pub trait Testable {
+  fn run(&self);
+}
+impl<T: Fn()> Testable for T {
+  fn run(&self) {
+    let name = type_name::<T>();
+    let mut log = DebugLog::new();
+    let _ = writeln!(log, "test {} ...", name);
+    self();
+    let _ = writeln!(log, "test {} ... ok", name);
+  }
+}
+
+/// Runs every test, then exits with code 0.
+///
+/// Use this as the `#![test_runner]`. A failing test panics, and the panic
+/// handler should call [`test_panic`].
+pub fn test_runner(tests: &[&dyn Testable]) {
+  let mut log = DebugLog::new();
+  let _ = writeln!(log, "running {} tests", tests.len());
+  for test in tests {
+    test.run();
+  }
+  let _ = writeln!(log, "test result: ok. {} passed", tests.len());
+  exit(0)
+}
+
+/// Reports a failed test and exits with code 1.
+///
+/// Call this from the test ROM's `#[panic_handler]`.
+pub fn test_panic(info: &PanicInfo) -> ! {
+  let mut log = DebugLog::new().with_level(DebugLevel::Error);
+  // the runner reads a line at a time, so keep the message on one line.
+  let _ = write!(OneLine(&mut log), "test failed: {}", info);
+  let _ = writeln!(log);
+  let _ = writeln!(log, "test result: FAILED");
+  exit(1)
+}
+
+/// Ends the test run with an exit code (0 for a pass).
+///
+/// On hardware (or anything but `mgba-rom-test`) this just stops, with
+/// [`halt_forever`].
+pub fn exit(code: u32) -> ! {
+  #[cfg(target_feature = "thumb-mode")]
+  const SWI_COMMENT: usize = EXIT_SWI;
+  #[cfg(not(target_feature = "thumb-mode"))]
+  const SWI_COMMENT: usize = EXIT_SWI << 16;
+  unsafe {
+    asm!(
+      "swi #{n}",
+      n = const SWI_COMMENT,
+      in("r0") code,
+      out("r1") _,
+      out("r3") _,
+    )
+  };
+  halt_forever()
+}
+
+/// Writes to a log with newlines turned into spaces.
+struct OneLine<'a>(&'a mut DebugLog);
+impl Write for OneLine<'_> {
+  fn write_str(&mut self, s: &str) -> core::fmt::Result {
+    for (i, part) in s.split('\n').enumerate() {
+      if i > 0 {
+        self.0.write_str(" ")?;
+      }
+      self.0.write_str(part)?;
+    }
+    Ok(())
+  }
+}
+
+/// The `main` of the library's own test ROM.
+#[cfg(all(test, target_arch = "arm"))]
+#[no_mangle]
+extern "C" fn main() -> ! {
+  crate::test_main();
+  exit(0)
+}
+
+#[cfg(all(test, target_arch = "arm"))]
+#[panic_handler]
+fn panic_handler(info: &PanicInfo) -> ! {
+  test_panic(info)
+}
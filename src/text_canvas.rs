@@ -0,0 +1,290 @@
+//! Drawing proportional text into tiles.
+
+use voladdress::{Safe, VolAddress, VolRegion};
+
+use crate::{
+  gba::{BgCharblock, TextScreenEntry, TextScreenblock, Tile4},
+  Align, LineBreaker,
+};
+
+/// A font of 256 glyphs, each up to 8x8 pixels, with its own width.
+///
+/// Each glyph is stored as 8 rows of 1bpp pixels, with bit 0 being the
+/// leftmost pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProportionalFont {
+  rows: [[u8; 8]; 256],
+  advances: [u8; 256],
+}
+impl ProportionalFont {
+  /// Makes a font from glyph rows and the advance (width plus spacing) of
+  /// each glyph in pixels.
+  #[inline]
+  #[must_use]
+  pub const fn new(rows: [[u8; 8]; 256], advances: [u8; 256]) -> Self {
+    Self { rows, advances }
+  }
+
+  /// Makes a font out of 256 4bpp tiles, such as the tiles from
+  /// [`decompress_cp437_data_to`].
+  ///
+  /// Any non-zero pixel counts as part of the glyph. Each glyph is moved to
+  /// the left edge, and given an advance of its width plus 1 pixel. Blank
+  /// glyphs get an advance of `space_advance`.
+  ///
+  /// ## Panics
+  /// * If there's less than 256 tiles.
+  ///
+  /// [`decompress_cp437_data_to`]: crate::gba::decompress_cp437_data_to
+  #[must_use]
+  pub fn from_tile4s(
+    tiles: VolRegion<Tile4, Safe, Safe>, space_advance: u8,
+  ) -> Self {
+    assert!(tiles.len() >= 256, "a font needs 256 tiles");
+    let mut rows = [[0_u8; 8]; 256];
+    let mut advances = [space_advance; 256];
+    for (i, glyph) in rows.iter_mut().enumerate() {
+      let tile = tiles.index(i).read();
+      let mut all_columns = 0_u8;
+      for (row, word) in glyph.iter_mut().zip(tile) {
+        for x in 0..8 {
+          if (word >> (x * 4)) & 0xF != 0 {
+            *row |= 1 << x;
+          }
+        }
+        all_columns |= *row;
+      }
+      if all_columns != 0 {
+        let left = all_columns.trailing_zeros();
+        let right = 7 - all_columns.leading_zeros();
+        for row in glyph.iter_mut() {
+          *row >>= left;
+        }
+        advances[i] = (right - left + 2) as u8;
+      }
+    }
+    Self { rows, advances }
+  }
+
+  /// How far to move right after drawing a glyph, in pixels.
+  #[inline]
+  #[must_use]
+  pub const fn advance(&self, byte: u8) -> u32 {
+    self.advances[byte as usize] as u32
+  }
+
+  /// The 8 rows of a glyph's pixels.
+  #[inline]
+  #[must_use]
+  pub const fn glyph(&self, byte: u8) -> [u8; 8] {
+    self.rows[byte as usize]
+  }
+
+  /// The width of some text, in pixels.
+  #[inline]
+  #[must_use]
+  pub fn text_width(&self, bytes: &[u8]) -> u32 {
+    bytes.iter().map(|&b| self.advance(b)).sum()
+  }
+}
+
+/// A rectangle of tiles that text can be drawn into pixel by pixel.
+///
+/// Instead of using one tile per character, the canvas uses a run of tiles
+/// in a charblock (one per cell of the rectangle) and draws glyphs into
+/// them, so glyphs can be any width and go anywhere. Use
+/// [`map_to`](Self::map_to) to point part of a screenblock at the tiles.
+///
+/// Text is drawn with one palette index (the ink), and the rest of each tile
+/// is left as it was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextCanvas {
+  charblock: BgCharblock,
+  first_tile: usize,
+  width: usize,
+  height: usize,
+  ink: u8,
+}
+impl TextCanvas {
+  /// Makes a canvas `width` by `height` tiles, using the tiles starting at
+  /// `first_tile` in the charblock.
+  ///
+  /// ## Failure
+  /// * If `width` or `height` are 0 or more than 32.
+  /// * If there aren't enough tiles in the charblock.
+  #[inline]
+  #[must_use]
+  pub const fn try_new(
+    charblock: BgCharblock, first_tile: usize, width: usize, height: usize,
+  ) -> Option<Self> {
+    let tiles = charblock.tiles4().len();
+    if width == 0
+      || width > 32
+      || height == 0
+      || height > 32
+      || first_tile + width * height > tiles
+    {
+      None
+    } else {
+      Some(Self { charblock, first_tile, width, height, ink: 1 })
+    }
+  }
+  /// As [`try_new`](Self::try_new), but panics on failure.
+  ///
+  /// ## Panics
+  /// * If the canvas can't be made.
+  #[inline]
+  #[must_use]
+  pub const fn new(
+    charblock: BgCharblock, first_tile: usize, width: usize, height: usize,
+  ) -> Self {
+    match Self::try_new(charblock, first_tile, width, height) {
+      Some(canvas) => canvas,
+      None => panic!("illegal text canvas"),
+    }
+  }
+  /// Sets the palette index that text is drawn with.
+  #[inline]
+  #[must_use]
+  pub const fn with_ink(self, ink: u8) -> Self {
+    Self { ink: ink & 0xF, ..self }
+  }
+
+  /// The width of the canvas, in pixels.
+  #[inline]
+  #[must_use]
+  pub const fn width_px(&self) -> u32 {
+    (self.width * 8) as u32
+  }
+  /// The height of the canvas, in pixels.
+  #[inline]
+  #[must_use]
+  pub const fn height_px(&self) -> u32 {
+    (self.height * 8) as u32
+  }
+
+  /// Points part of a screenblock at the canvas tiles, with the canvas's
+  /// top left at `(x, y)`.
+  ///
+  /// Any part of the canvas that goes off the screenblock is skipped.
+  pub fn map_to(
+    &self, screenblock: TextScreenblock, x: usize, y: usize, palbank: u16,
+  ) {
+    let block = screenblock.as_volblock();
+    for row in 0..self.height {
+      for col in 0..self.width {
+        if x + col < 32 && y + row < 32 {
+          let id = (self.first_tile + row * self.width + col) as u16;
+          block
+            .index((y + row) * 32 + x + col)
+            .write(TextScreenEntry::from_id_bank(id, palbank));
+        }
+      }
+    }
+  }
+
+  /// Sets every pixel of the canvas to 0.
+  pub fn clear(&self) {
+    let tiles = self.charblock.tiles4();
+    for i in 0..(self.width * self.height) {
+      tiles.index(self.first_tile + i).write([0; 8]);
+    }
+  }
+
+  /// Draws one glyph with its top left at pixel `(x, y)`.
+  ///
+  /// Pixels outside the canvas are skipped. Returns the glyph's advance.
+  pub fn draw_glyph(
+    &self, font: &ProportionalFont, x: u32, y: u32, byte: u8,
+  ) -> u32 {
+    let ink = (self.ink as u32) * 0x1111_1111;
+    for (dy, bits) in font.glyph(byte).iter().copied().enumerate() {
+      let py = y as usize + dy;
+      if bits == 0 || py >= self.height * 8 {
+        continue;
+      }
+      let tile_x = x as usize / 8;
+      let spread = (bits as u16) << (x % 8);
+      for (tx, part) in
+        [(tile_x, spread as u8), (tile_x + 1, (spread >> 8) as u8)]
+      {
+        if part == 0 || tx >= self.width {
+          continue;
+        }
+        let mask = nibble_mask(part);
+        let word = self.row_address(tx, py);
+        word.write((word.read() & !mask) | (ink & mask));
+      }
+    }
+    font.advance(byte)
+  }
+
+  /// Draws a line of text with its top left at pixel `(x, y)`.
+  ///
+  /// Returns the width of the text.
+  pub fn draw_text(
+    &self, font: &ProportionalFont, x: u32, y: u32, bytes: &[u8],
+  ) -> u32 {
+    let mut pen = x;
+    for byte in bytes.iter().copied() {
+      pen += self.draw_glyph(font, pen, y, byte);
+    }
+    pen - x
+  }
+
+  /// Lays text out over the whole canvas, wrapping lines between words.
+  ///
+  /// Lines are `line_height` pixels apart and aligned within the canvas's
+  /// width. The canvas isn't cleared first.
+  ///
+  /// Returns how many bytes of the text fit, so that the rest can be shown
+  /// later.
+  pub fn draw_wrapped(
+    &self, font: &ProportionalFont, bytes: &[u8], align: Align,
+    line_height: u32,
+  ) -> usize {
+    let width = self.width_px();
+    let mut lines = LineBreaker::new(bytes, width, |b| font.advance(b));
+    let mut y = 0;
+    while y + 8 <= self.height_px() {
+      let line = match lines.next() {
+        Some(line) => line,
+        None => break,
+      };
+      let x = align.offset(line.width, width);
+      let x = x + self.draw_text(font, x, y, line.text);
+      if line.hyphen {
+        self.draw_glyph(font, x, y, b'-');
+      }
+      y += line_height;
+    }
+    lines.position()
+  }
+
+  /// The address of one row of one canvas tile.
+  #[inline]
+  #[must_use]
+  fn row_address(
+    &self, tile_x: usize, py: usize,
+  ) -> VolAddress<u32, Safe, Safe> {
+    let tile = self.first_tile + (py / 8) * self.width + tile_x;
+    let addr = self.charblock.tiles4().index(tile).as_usize() + (py % 8) * 4;
+    // Safety: this is inside the tile, which is inside the charblock.
+    unsafe { VolAddress::new(addr) }
+  }
+}
+
+/// Turns 8 pixel bits into a mask of the 8 matching 4bpp pixels.
+#[inline]
+#[must_use]
+const fn nibble_mask(bits: u8) -> u32 {
+  let mut mask = 0;
+  let mut x = 0;
+  while x < 8 {
+    if bits & (1 << x) != 0 {
+      mask |= 0xF << (x * 4);
+    }
+    x += 1;
+  }
+  mask
+}
@@ -0,0 +1,682 @@
+//! A text console drawn on a text background.
+
+use crate::{
+  char_to_cp437,
+  gba::{Keys, TextScreenEntry, TextScreenblock},
+  Align, AnsiEvent, AnsiParams, AnsiParser, LineBreaker, Scrollback,
+};
+
+/// A grid of characters that's drawn into part of a [`TextScreenblock`].
+///
+/// The console is `W` columns by `H` rows (each at most 32), and it keeps its
+/// own copy of the characters and palbanks so that writing text never has to
+/// touch VRAM. Call [`flush`](Self::flush) during VBlank to copy just the
+/// rows that changed into the screenblock.
+///
+/// Each character is drawn with tile `font_offset + byte`, so a 256 tile font
+/// (such as the one from [`decompress_cp437_data_to`]) can be put at any
+/// point in the background's charblock.
+///
+/// When text goes past the bottom row, everything moves up a row.
+///
+/// Text written with [`core::fmt::Write`] is translated to CP437 with
+/// [`char_to_cp437`], and any character that's not in CP437 is drawn as the
+/// fallback glyph (`?` unless you pick another).
+///
+/// That text is also run through an [`AnsiParser`], so it can use escape
+/// sequences to move the cursor, erase, and change colors. SGR colors pick a
+/// palbank, assuming that banks 0 to 7 are set up with the 8 ANSI colors
+/// (black, red, green, yellow, blue, magenta, cyan, white) and banks 8 to 15
+/// are dim versions of the same colors:
+/// * `30` to `37` and `90` to `97` use banks 0 to 7.
+/// * `2` (faint) uses banks 8 to 15 until `1`, `22`, or `0`.
+/// * `39` and `0` go back to the console's default palbank.
+/// * Background colors are ignored, since every palbank shares the backdrop
+///   color.
+///
+/// If the console has a [`Scrollback`], rows that move off the top are saved
+/// there, and the console can be switched to viewing older lines with
+/// [`scroll_view`](Self::scroll_view) (or
+/// [`handle_view_keys`](Self::handle_view_keys)). New text still goes into
+/// the live rows while older lines are being viewed.
+///
+/// [`decompress_cp437_data_to`]: crate::gba::decompress_cp437_data_to
+#[derive(Debug)]
+pub struct TextConsole<const W: usize, const H: usize> {
+  chars: [[u8; W]; H],
+  banks: [[u8; W]; H],
+  dirty: u32,
+  cursor_x: usize,
+  cursor_y: usize,
+  palbank: u8,
+  default_palbank: u8,
+  screenblock: TextScreenblock,
+  origin_x: usize,
+  origin_y: usize,
+  font_offset: u16,
+  fallback: u8,
+  parser: AnsiParser,
+  saved_cursor: (usize, usize),
+  sgr_color: Option<u8>,
+  sgr_faint: bool,
+  history: Option<Scrollback>,
+  view_offset: usize,
+}
+impl<const W: usize, const H: usize> TextConsole<W, H> {
+  /// Makes a blank console in the top left of the screenblock.
+  ///
+  /// ## Panics
+  /// * If `W` or `H` are 0 or more than 32.
+  #[inline]
+  #[must_use]
+  pub const fn new(screenblock: TextScreenblock) -> Self {
+    assert!(W > 0 && W <= 32, "console width must be 1 to 32");
+    assert!(H > 0 && H <= 32, "console height must be 1 to 32");
+    Self {
+      chars: [[b' '; W]; H],
+      banks: [[0; W]; H],
+      dirty: u32::MAX,
+      cursor_x: 0,
+      cursor_y: 0,
+      palbank: 0,
+      default_palbank: 0,
+      screenblock,
+      origin_x: 0,
+      origin_y: 0,
+      font_offset: 0,
+      fallback: b'?',
+      parser: AnsiParser::new(),
+      saved_cursor: (0, 0),
+      sgr_color: None,
+      sgr_faint: false,
+      history: None,
+      view_offset: 0,
+    }
+  }
+
+  /// Moves where the console's top left corner is in the screenblock.
+  ///
+  /// ## Panics
+  /// * If the console wouldn't fit in the screenblock.
+  #[inline]
+  #[must_use]
+  pub fn with_origin(self, x: usize, y: usize) -> Self {
+    assert!(x + W <= 32 && y + H <= 32, "console must fit in the screenblock");
+    Self { origin_x: x, origin_y: y, dirty: u32::MAX, ..self }
+  }
+
+  /// Sets the tile index of the font's first glyph.
+  #[inline]
+  #[must_use]
+  pub fn with_font_offset(self, font_offset: u16) -> Self {
+    Self { font_offset, dirty: u32::MAX, ..self }
+  }
+
+  /// Sets the glyph drawn for characters that aren't in CP437.
+  #[inline]
+  #[must_use]
+  pub fn with_fallback(self, fallback: u8) -> Self {
+    Self { fallback, ..self }
+  }
+
+  /// Gives the console a scrollback to keep rows that move off the top.
+  #[inline]
+  #[must_use]
+  pub fn with_scrollback(self, history: Scrollback) -> Self {
+    Self { history: Some(history), view_offset: 0, ..self }
+  }
+
+  /// Sets the palbank used for new text, and for clearing.
+  #[inline]
+  #[must_use]
+  pub fn with_palbank(self, palbank: u8) -> Self {
+    let palbank = palbank & 0xF;
+    Self { palbank, default_palbank: palbank, ..self }
+  }
+
+  #[inline]
+  #[must_use]
+  pub fn scrollback(&self) -> Option<&Scrollback> {
+    self.history.as_ref()
+  }
+  /// Takes the scrollback out of the console, going back to the live view.
+  #[inline]
+  pub fn take_scrollback(&mut self) -> Option<Scrollback> {
+    self.snap_to_live();
+    self.history.take()
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn width(&self) -> usize {
+    W
+  }
+  #[inline]
+  #[must_use]
+  pub const fn height(&self) -> usize {
+    H
+  }
+
+  /// The `(column, row)` where the next character will go.
+  #[inline]
+  #[must_use]
+  pub const fn cursor(&self) -> (usize, usize) {
+    (self.cursor_x, self.cursor_y)
+  }
+  /// Moves the cursor, clamping it to the console's size.
+  #[inline]
+  pub fn set_cursor(&mut self, x: usize, y: usize) {
+    self.cursor_x = x.min(W - 1);
+    self.cursor_y = y.min(H - 1);
+  }
+
+  /// The palbank used for new text.
+  #[inline]
+  #[must_use]
+  pub const fn palbank(&self) -> u8 {
+    self.palbank
+  }
+  #[inline]
+  pub fn set_palbank(&mut self, palbank: u8) {
+    self.palbank = palbank & 0xF;
+  }
+  /// Goes back to the palbank set with [`with_palbank`](Self::with_palbank).
+  #[inline]
+  pub fn reset_palbank(&mut self) {
+    self.palbank = self.default_palbank;
+  }
+
+  /// The character and palbank at a position.
+  #[inline]
+  #[must_use]
+  pub fn get(&self, x: usize, y: usize) -> Option<(u8, u8)> {
+    let ch = *self.chars.get(y)?.get(x)?;
+    Some((ch, self.banks[y][x]))
+  }
+  /// Sets the character and palbank at a position, without moving the cursor.
+  #[inline]
+  pub fn set(&mut self, x: usize, y: usize, ch: u8, palbank: u8) {
+    if x < W && y < H {
+      self.chars[y][x] = ch;
+      self.banks[y][x] = palbank & 0xF;
+      self.dirty |= 1 << y;
+    }
+  }
+
+  /// Writes a byte at the cursor and advances it.
+  ///
+  /// The byte is always drawn as a glyph, even if it's a control code. Use
+  /// [`push_bytes`](Self::push_bytes) to handle newlines.
+  #[inline]
+  pub fn put_byte(&mut self, byte: u8) {
+    self.set(self.cursor_x, self.cursor_y, byte, self.palbank);
+    self.cursor_x += 1;
+    if self.cursor_x >= W {
+      self.newline();
+    }
+  }
+
+  /// Writes bytes at the cursor.
+  ///
+  /// * `\n` moves to the start of the next line.
+  /// * `\r` moves to the start of the current line.
+  /// * Anything else is drawn as a glyph.
+  #[inline]
+  pub fn push_bytes(&mut self, bytes: &[u8]) {
+    for byte in bytes.iter().copied() {
+      match byte {
+        b'\n' => self.newline(),
+        b'\r' => self.cursor_x = 0,
+        other => self.put_byte(other),
+      }
+    }
+  }
+
+  /// Writes bytes at the cursor, wrapping lines between words.
+  ///
+  /// This lays the text out with a [`LineBreaker`], so lines break at spaces
+  /// and hyphens instead of in the middle of words. `\n` moves to the start
+  /// of the next line, but other control codes are drawn as glyphs.
+  pub fn push_wrapped(&mut self, bytes: &[u8]) {
+    let mut lines = LineBreaker::new(bytes, (W - self.cursor_x) as u32, |_| 1);
+    let mut first = true;
+    // if the last line exactly filled a row, the cursor already moved down
+    let mut wrapped = false;
+    while let Some(line) = lines.next() {
+      if !first && !wrapped {
+        self.newline();
+      }
+      first = false;
+      lines.set_max_width(W as u32);
+      for byte in line.text.iter().copied() {
+        self.put_byte(byte);
+      }
+      if line.hyphen {
+        self.put_byte(b'-');
+      }
+      wrapped = self.cursor_x == 0 && line.width > 0;
+    }
+  }
+
+  /// Lays text out in a rectangle, wrapping lines between words.
+  ///
+  /// Each line is aligned within the rectangle's width, and the rectangle is
+  /// cleared first. The cursor doesn't move.
+  ///
+  /// Returns how many bytes of the text fit in the rectangle, so that the
+  /// rest can be shown later.
+  pub fn write_aligned(
+    &mut self, x: usize, y: usize, w: usize, h: usize, bytes: &[u8],
+    align: Align,
+  ) -> usize {
+    self.clear_region(x, y, w, h);
+    let mut lines = LineBreaker::new(bytes, w as u32, |_| 1);
+    for row in y..(y + h) {
+      let line = match lines.next() {
+        Some(line) => line,
+        None => break,
+      };
+      let mut col = x + align.offset(line.width, w as u32) as usize;
+      for byte in line.text.iter().copied() {
+        self.set(col, row, byte, self.palbank);
+        col += 1;
+      }
+      if line.hyphen {
+        self.set(col, row, b'-', self.palbank);
+      }
+    }
+    lines.position()
+  }
+
+  /// Moves the cursor to the start of the next line, scrolling if needed.
+  #[inline]
+  pub fn newline(&mut self) {
+    self.cursor_x = 0;
+    if self.cursor_y + 1 < H {
+      self.cursor_y += 1;
+    } else {
+      self.scroll_up(1);
+    }
+  }
+
+  /// Moves all text up `n` rows, clearing the rows at the bottom.
+  ///
+  /// The cursor doesn't move.
+  #[inline]
+  pub fn scroll_up(&mut self, n: usize) {
+    let n = n.min(H);
+    if let Some(history) = self.history.as_mut() {
+      for y in 0..n {
+        history.push_line(&self.chars[y], &self.banks[y]);
+      }
+      if self.view_offset > 0 {
+        self.view_offset = (self.view_offset + n).min(history.len());
+      }
+    }
+    self.chars.copy_within(n.., 0);
+    self.banks.copy_within(n.., 0);
+    self.clear_region(0, H - n, W, n);
+    self.dirty = u32::MAX;
+  }
+
+  /// Clears all text and moves the cursor to the top left.
+  #[inline]
+  pub fn clear(&mut self) {
+    self.clear_region(0, 0, W, H);
+    self.cursor_x = 0;
+    self.cursor_y = 0;
+  }
+
+  /// Clears one row.
+  #[inline]
+  pub fn clear_line(&mut self, y: usize) {
+    self.clear_region(0, y, W, 1);
+  }
+
+  /// Clears a rectangle, which is clipped to the console's size.
+  #[inline]
+  pub fn clear_region(&mut self, x: usize, y: usize, w: usize, h: usize) {
+    let x_end = x.saturating_add(w).min(W);
+    let y_end = y.saturating_add(h).min(H);
+    for row in y.min(H)..y_end {
+      self.chars[row][x.min(W)..x_end].fill(b' ');
+      self.banks[row][x.min(W)..x_end].fill(self.default_palbank);
+      self.dirty |= 1 << row;
+    }
+  }
+
+  /// Handles one event from the console's escape sequence parser.
+  pub fn apply_event(&mut self, event: AnsiEvent) {
+    let (x, y) = self.cursor();
+    match event {
+      AnsiEvent::Print(c) => {
+        self.put_byte(char_to_cp437(c).unwrap_or(self.fallback))
+      }
+      AnsiEvent::Control(b'\n') => self.newline(),
+      AnsiEvent::Control(b'\r') => self.cursor_x = 0,
+      AnsiEvent::Control(b'\x08') => self.cursor_x = x.saturating_sub(1),
+      AnsiEvent::Control(b'\t') => {
+        let next_stop = (x + 8) & !7;
+        if next_stop < W {
+          self.cursor_x = next_stop;
+        } else {
+          self.newline();
+        }
+      }
+      AnsiEvent::Control(b'\x07') => (),
+      AnsiEvent::Control(other) => self.put_byte(other),
+      AnsiEvent::CursorUp(n) => self.set_cursor(x, y.saturating_sub(n.into())),
+      AnsiEvent::CursorDown(n) => self.set_cursor(x, y + n as usize),
+      AnsiEvent::CursorForward(n) => self.set_cursor(x + n as usize, y),
+      AnsiEvent::CursorBack(n) => {
+        self.set_cursor(x.saturating_sub(n.into()), y)
+      }
+      AnsiEvent::CursorPosition { row, col } => {
+        self.set_cursor(col.into(), row.into())
+      }
+      AnsiEvent::EraseInDisplay(0) => {
+        self.clear_region(x, y, W, 1);
+        self.clear_region(0, y + 1, W, H);
+      }
+      AnsiEvent::EraseInDisplay(1) => {
+        self.clear_region(0, 0, W, y);
+        self.clear_region(0, y, x + 1, 1);
+      }
+      AnsiEvent::EraseInDisplay(_) => self.clear_region(0, 0, W, H),
+      AnsiEvent::EraseInLine(0) => self.clear_region(x, y, W, 1),
+      AnsiEvent::EraseInLine(1) => self.clear_region(0, y, x + 1, 1),
+      AnsiEvent::EraseInLine(_) => self.clear_line(y),
+      AnsiEvent::SelectGraphicRendition(params) => {
+        self.select_graphic_rendition(&params)
+      }
+      AnsiEvent::SaveCursor => self.saved_cursor = (x, y),
+      AnsiEvent::RestoreCursor => {
+        let (x, y) = self.saved_cursor;
+        self.set_cursor(x, y);
+      }
+    }
+  }
+
+  fn select_graphic_rendition(&mut self, params: &AnsiParams) {
+    let values = params.as_slice();
+    if values.is_empty() {
+      self.sgr_color = None;
+      self.sgr_faint = false;
+    }
+    for v in values.iter().copied() {
+      match v {
+        0 => {
+          self.sgr_color = None;
+          self.sgr_faint = false;
+        }
+        1 | 22 => self.sgr_faint = false,
+        2 => self.sgr_faint = true,
+        30..=37 => self.sgr_color = Some((v - 30) as u8),
+        39 => self.sgr_color = None,
+        90..=97 => {
+          self.sgr_color = Some((v - 90) as u8);
+          self.sgr_faint = false;
+        }
+        _ => (),
+      }
+    }
+    let base = match self.sgr_color {
+      Some(color) => color,
+      None => self.default_palbank,
+    };
+    self.palbank = if self.sgr_faint { (base | 8) & 0xF } else { base };
+  }
+
+  /// How many lines back from the live rows the view is.
+  #[inline]
+  #[must_use]
+  pub const fn view_offset(&self) -> usize {
+    self.view_offset
+  }
+
+  /// Moves the view back into the scrollback by `delta` lines (or forward, if
+  /// `delta` is negative).
+  ///
+  /// The view stays between the live rows and the oldest scrollback line.
+  #[inline]
+  pub fn scroll_view(&mut self, delta: isize) {
+    let limit = self.history.as_ref().map_or(0, Scrollback::len);
+    let new_offset = if delta < 0 {
+      self.view_offset.saturating_sub(delta.unsigned_abs())
+    } else {
+      self.view_offset.saturating_add(delta as usize).min(limit)
+    };
+    if new_offset != self.view_offset {
+      self.view_offset = new_offset;
+      self.dirty = u32::MAX;
+    }
+  }
+
+  /// Goes back to viewing the live rows.
+  #[inline]
+  pub fn snap_to_live(&mut self) {
+    self.scroll_view(-(self.view_offset as isize));
+  }
+
+  /// Moves the view based on newly pressed keys.
+  ///
+  /// * L and R go back and forward a page.
+  /// * Up and Down go back and forward a line.
+  /// * A, B, Start, and Select go back to the live rows.
+  #[inline]
+  pub fn handle_view_keys(&mut self, pressed: Keys) {
+    if pressed.a() || pressed.b() || pressed.start() || pressed.select() {
+      self.snap_to_live();
+      return;
+    }
+    if pressed.l() {
+      self.scroll_view(H as isize);
+    }
+    if pressed.r() {
+      self.scroll_view(-(H as isize));
+    }
+    if pressed.up() {
+      self.scroll_view(1);
+    }
+    if pressed.down() {
+      self.scroll_view(-1);
+    }
+  }
+
+  /// Marks every row as needing to be flushed.
+  #[inline]
+  pub fn mark_all_dirty(&mut self) {
+    self.dirty = u32::MAX;
+  }
+
+  /// Copies every row that's changed since the last flush into VRAM.
+  ///
+  /// Call this during VBlank.
+  #[inline]
+  pub fn flush(&mut self) {
+    if self.view_offset > 0 && self.dirty != 0 {
+      // the screen rows don't line up with the live rows, so redraw it all.
+      self.dirty = u32::MAX;
+    }
+    let block = self.screenblock.as_volblock();
+    for y in 0..H {
+      if (self.dirty & (1 << y)) == 0 {
+        continue;
+      }
+      let (chars, banks): (&[u8], &[u8]) = if y < self.view_offset {
+        // the history can't be empty if the view offset is non-zero.
+        let history = self.history.as_ref().unwrap();
+        let (chars, banks) =
+          history.line(history.len() + y - self.view_offset).unwrap();
+        (&chars[..W], &banks[..W])
+      } else {
+        let live_y = y - self.view_offset;
+        (&self.chars[live_y], &self.banks[live_y])
+      };
+      let row_start = (self.origin_y + y) * 32 + self.origin_x;
+      let row = chars.iter().zip(banks.iter());
+      for (x, (ch, bank)) in row.enumerate() {
+        let id = self.font_offset.wrapping_add(*ch as u16);
+        let entry = TextScreenEntry::from_id_bank(id, *bank as u16);
+        block.index(row_start + x).write(entry);
+      }
+    }
+    self.dirty = 0;
+  }
+}
+impl<const W: usize, const H: usize> core::fmt::Write for TextConsole<W, H> {
+  #[inline]
+  fn write_str(&mut self, s: &str) -> core::fmt::Result {
+    for c in s.chars() {
+      self.write_char(c)?;
+    }
+    Ok(())
+  }
+  #[inline]
+  fn write_char(&mut self, c: char) -> core::fmt::Result {
+    if let Some(event) = self.parser.advance(c) {
+      self.apply_event(event);
+    }
+    Ok(())
+  }
+}
+
+#[cfg(all(test, not(target_arch = "arm")))]
+mod tests {
+  extern crate std;
+
+  use super::*;
+  use core::fmt::Write;
+  use std::{string::String, vec, vec::Vec};
+
+  type Small = TextConsole<4, 3>;
+
+  /// The console's characters, one string per row.
+  fn rows<const W: usize, const H: usize>(
+    console: &TextConsole<W, H>,
+  ) -> Vec<String> {
+    (0..H)
+      .map(|y| {
+        (0..W).map(|x| char::from(console.get(x, y).unwrap().0)).collect()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn writing_moves_the_cursor() {
+    let mut console = Small::new(TextScreenblock::_31);
+    write!(console, "ab").unwrap();
+    assert_eq!(console.cursor(), (2, 0));
+    assert_eq!(rows(&console), vec!["ab  ", "    ", "    "]);
+    assert_eq!(console.get(4, 0), None);
+    assert_eq!(console.get(0, 3), None);
+  }
+
+  #[test]
+  fn wraps_at_the_width() {
+    let mut console = Small::new(TextScreenblock::_31);
+    write!(console, "abcd").unwrap();
+    assert_eq!(console.cursor(), (0, 1));
+    write!(console, "ef").unwrap();
+    assert_eq!(console.cursor(), (2, 1));
+    assert_eq!(rows(&console), vec!["abcd", "ef  ", "    "]);
+  }
+
+  #[test]
+  fn scrolls_past_the_bottom() {
+    let mut console = Small::new(TextScreenblock::_31);
+    write!(console, "a\nb\nc").unwrap();
+    assert_eq!(console.cursor(), (1, 2));
+    write!(console, "\nd").unwrap();
+    assert_eq!(console.cursor(), (1, 2));
+    assert_eq!(rows(&console), vec!["b   ", "c   ", "d   "]);
+    console.scroll_up(5);
+    assert_eq!(rows(&console), vec!["    ", "    ", "    "]);
+  }
+
+  #[test]
+  fn control_codes() {
+    let mut console = TextConsole::<12, 2>::new(TextScreenblock::_31);
+    write!(console, "abc\rX\x08\x08Y\x07").unwrap();
+    assert_eq!(console.cursor(), (1, 0));
+    write!(console, "\tt\tu").unwrap();
+    assert_eq!(rows(&console), vec!["Ybc     t   ", "u           "]);
+  }
+
+  #[test]
+  fn escape_sequences_move_and_erase() {
+    let mut console = Small::new(TextScreenblock::_31);
+    write!(console, "abcdefgh\x1b[2;3HX").unwrap();
+    assert_eq!(rows(&console), vec!["abcd", "efXh", "    "]);
+    write!(console, "\x1b[1;2H\x1b[K").unwrap();
+    assert_eq!(rows(&console), vec!["a   ", "efXh", "    "]);
+    write!(console, "\x1b[s\x1b[9;9H").unwrap();
+    assert_eq!(console.cursor(), (3, 2));
+    write!(console, "\x1b[u\x1b[B\x1b[1K").unwrap();
+    assert_eq!(console.cursor(), (1, 1));
+    assert_eq!(rows(&console), vec!["a   ", "  Xh", "    "]);
+    write!(console, "\x1b[2J").unwrap();
+    assert_eq!(rows(&console), vec!["    ", "    ", "    "]);
+  }
+
+  #[test]
+  fn sgr_picks_palbanks() {
+    let mut console = Small::new(TextScreenblock::_31).with_palbank(3);
+    write!(console, "a\x1b[31mb\x1b[2mc\x1b[22md").unwrap();
+    write!(console, "\x1b[94me\x1b[2;39mf\x1b[0mg\x1b[32;1mh").unwrap();
+    let banks: Vec<u8> =
+      (0..8).map(|i| console.get(i % 4, i / 4).unwrap().1).collect();
+    assert_eq!(banks, vec![3, 1, 9, 1, 4, 11, 3, 2]);
+    write!(console, "\x1b[m").unwrap();
+    assert_eq!(console.palbank(), 3);
+  }
+
+  #[test]
+  fn unmapped_chars_use_the_fallback() {
+    let mut console = Small::new(TextScreenblock::_31);
+    write!(console, "é☃").unwrap();
+    console = console.with_fallback(b'#');
+    write!(console, "☃").unwrap();
+    assert_eq!(rows(&console)[0], "\u{82}?# ");
+  }
+
+  #[test]
+  fn push_wrapped_breaks_between_words() {
+    let mut console = TextConsole::<8, 4>::new(TextScreenblock::_31);
+    console.push_bytes(b"> ");
+    console.push_wrapped(b"hello big world");
+    assert_eq!(
+      rows(&console),
+      vec!["> hello ", "big     ", "world   ", "        "]
+    );
+    assert_eq!(console.cursor(), (5, 2));
+  }
+
+  #[test]
+  fn push_wrapped_exact_rows() {
+    let mut console = Small::new(TextScreenblock::_31);
+    console.push_wrapped(b"abcd efg");
+    assert_eq!(rows(&console), vec!["abcd", "efg ", "    "]);
+    assert_eq!(console.cursor(), (3, 1));
+  }
+
+  #[test]
+  fn write_aligned_reports_what_fit() {
+    let mut console = TextConsole::<6, 3>::new(TextScreenblock::_31);
+    console.set_cursor(2, 2);
+    let used =
+      console.write_aligned(0, 0, 6, 2, b"ab cd ef gh ij", Align::Right);
+    assert_eq!(used, 12);
+    assert_eq!(rows(&console), vec![" ab cd", " ef gh", "      "]);
+    assert_eq!(console.cursor(), (2, 2));
+  }
+
+  #[test]
+  fn no_scrollback_means_no_view() {
+    let mut console = Small::new(TextScreenblock::_31);
+    write!(console, "a\nb\nc\nd\n").unwrap();
+    console.scroll_view(3);
+    assert_eq!(console.view_offset(), 0);
+    assert!(console.take_scrollback().is_none());
+  }
+}
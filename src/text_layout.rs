@@ -0,0 +1,265 @@
+//! Breaking text into lines that fit a width.
+
+/// How lines are placed within the width they're laid out in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+  Left,
+  Center,
+  Right,
+}
+impl Align {
+  /// How far in from the left a line of `line_width` should start, when it's
+  /// placed in a space that's `box_width` wide.
+  #[inline]
+  #[must_use]
+  pub const fn offset(self, line_width: u32, box_width: u32) -> u32 {
+    let spare = box_width.saturating_sub(line_width);
+    match self {
+      Self::Left => 0,
+      Self::Center => spare / 2,
+      Self::Right => spare,
+    }
+  }
+}
+
+/// One line of text from a [`LineBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutLine<'a> {
+  /// The bytes of the line, without any trailing spaces or newline.
+  pub text: &'a [u8],
+  /// If a `-` should be drawn after the text, because a word was too long
+  /// to fit and had to be split.
+  pub hyphen: bool,
+  /// The width of the line, including the hyphen if there is one.
+  pub width: u32,
+}
+
+/// Splits text into lines no wider than a maximum width.
+///
+/// The width of each byte is given by the `advance` function, so this works
+/// with a fixed width font (where every byte is `1` column) as well as a
+/// proportional font (where each byte is some number of pixels).
+///
+/// * `\n` always ends a line.
+/// * Otherwise lines are broken at a space (which is dropped, along with any
+///   spaces at the start of the next line) or just after a `-`.
+/// * A word that's too long for a line by itself is split, and the line gets a
+///   hyphen added.
+///
+/// The maximum width can be changed between lines with
+/// [`set_max_width`](Self::set_max_width), for example if the first line
+/// starts part way across.
+#[derive(Debug, Clone)]
+pub struct LineBreaker<'a, F> {
+  text: &'a [u8],
+  pos: usize,
+  max_width: u32,
+  advance: F,
+  soft_break: bool,
+  done: bool,
+}
+impl<'a, F: Fn(u8) -> u32> LineBreaker<'a, F> {
+  #[inline]
+  #[must_use]
+  pub fn new(text: &'a [u8], max_width: u32, advance: F) -> Self {
+    Self { text, pos: 0, max_width, advance, soft_break: false, done: false }
+  }
+
+  /// Sets the maximum width of lines after this point.
+  #[inline]
+  pub fn set_max_width(&mut self, max_width: u32) {
+    self.max_width = max_width;
+  }
+
+  /// How many bytes of the text have been used up by the lines so far.
+  #[inline]
+  #[must_use]
+  pub const fn position(&self) -> usize {
+    self.pos
+  }
+
+  #[inline]
+  #[must_use]
+  fn width_of(&self, bytes: &[u8]) -> u32 {
+    bytes.iter().map(|&b| (self.advance)(b)).sum()
+  }
+
+  #[inline]
+  #[must_use]
+  fn line(&self, start: usize, end: usize, hyphen: bool) -> LayoutLine<'a> {
+    let mut end = end;
+    while end > start && self.text[end - 1] == b' ' {
+      end -= 1;
+    }
+    let text = &self.text[start..end];
+    let mut width = self.width_of(text);
+    if hyphen {
+      width += (self.advance)(b'-');
+    }
+    LayoutLine { text, hyphen, width }
+  }
+}
+impl<'a, F: Fn(u8) -> u32> Iterator for LineBreaker<'a, F> {
+  type Item = LayoutLine<'a>;
+
+  fn next(&mut self) -> Option<LayoutLine<'a>> {
+    if self.done {
+      return None;
+    }
+    let text = self.text;
+    if self.soft_break {
+      while self.pos < text.len() && text[self.pos] == b' ' {
+        self.pos += 1;
+      }
+      if self.pos == text.len() {
+        self.done = true;
+        return None;
+      }
+    }
+    let start = self.pos;
+    let mut width = 0;
+    // (end of this line, start of the next line)
+    let mut last_break: Option<(usize, usize)> = None;
+    // spaces before the first word aren't a place to break
+    let mut has_word = false;
+    let mut i = start;
+    loop {
+      if i == text.len() {
+        self.pos = i;
+        self.done = true;
+        return Some(self.line(start, i, false));
+      }
+      let byte = text[i];
+      if byte == b'\n' {
+        self.pos = i + 1;
+        self.soft_break = false;
+        return Some(self.line(start, i, false));
+      }
+      if byte == b' ' && has_word {
+        last_break = Some((i, i + 1));
+      }
+      let w = (self.advance)(byte);
+      if width + w > self.max_width && i > start {
+        self.soft_break = true;
+        if let Some((end, next)) = last_break {
+          self.pos = next;
+          return Some(self.line(start, end, false));
+        }
+        // no place to break, so split the word
+        let hyphen = (self.advance)(b'-');
+        let mut end = i;
+        while end > start + 1 && width + hyphen > self.max_width {
+          end -= 1;
+          width -= (self.advance)(text[end]);
+        }
+        self.pos = end;
+        return Some(self.line(start, end, true));
+      }
+      width += w;
+      i += 1;
+      has_word |= byte != b' ';
+      if byte == b'-' {
+        last_break = Some((i, i));
+      }
+    }
+  }
+}
+
+#[cfg(all(test, not(target_arch = "arm")))]
+mod tests {
+  extern crate std;
+
+  use super::*;
+  use quickcheck::quickcheck;
+  use std::{string::String, vec, vec::Vec};
+
+  /// The lines as strings, with a `-` on the end of split words.
+  fn lines(text: &str, max_width: u32) -> Vec<String> {
+    LineBreaker::new(text.as_bytes(), max_width, |_| 1)
+      .map(|line| {
+        let mut s = String::from_utf8(line.text.to_vec()).unwrap();
+        if line.hyphen {
+          s.push('-');
+        }
+        assert_eq!(line.width as usize, s.len());
+        s
+      })
+      .collect()
+  }
+
+  #[test]
+  fn breaks_at_spaces() {
+    assert_eq!(lines("hello big world", 9), vec!["hello big", "world"]);
+    assert_eq!(lines("hello big world", 5), vec!["hello", "big", "world"]);
+    assert_eq!(lines("hello big world", 20), vec!["hello big world"]);
+  }
+
+  #[test]
+  fn drops_spaces_around_a_break() {
+    assert_eq!(lines("one   two", 4), vec!["one", "two"]);
+    assert_eq!(lines("one two   ", 3), vec!["one", "two"]);
+  }
+
+  #[test]
+  fn newlines_always_break() {
+    assert_eq!(lines("a\n\nb", 10), vec!["a", "", "b"]);
+    assert_eq!(lines("a  \n  b", 10), vec!["a", "  b"]);
+  }
+
+  #[test]
+  fn breaks_after_a_hyphen() {
+    assert_eq!(lines("well-known fact", 8), vec!["well-", "known", "fact"]);
+  }
+
+  #[test]
+  fn splits_long_words() {
+    assert_eq!(lines("abcdefghij", 4), vec!["abc-", "def-", "ghij"]);
+    assert_eq!(lines("abcdefghijk", 4), vec!["abc-", "def-", "ghi-", "jk"]);
+    assert_eq!(lines("a abcdefgh", 4), vec!["a", "abc-", "def-", "gh"]);
+  }
+
+  #[test]
+  fn proportional_widths() {
+    let advance = |b: u8| match b {
+      b'i' => 2,
+      b' ' => 1,
+      _ => 5,
+    };
+    let widths: Vec<u32> = LineBreaker::new(b"ii ii mm", 10, advance)
+      .map(|line| line.width)
+      .collect();
+    assert_eq!(widths, vec![9, 10]);
+  }
+
+  #[test]
+  fn max_width_can_change_between_lines() {
+    let mut breaker = LineBreaker::new(b"aa bb cc dd", 2, |_| 1);
+    assert_eq!(breaker.next().unwrap().text, b"aa");
+    breaker.set_max_width(5);
+    assert_eq!(breaker.next().unwrap().text, b"bb cc");
+    assert_eq!(breaker.position(), 9);
+    assert_eq!(breaker.next().unwrap().text, b"dd");
+    assert_eq!(breaker.next(), None);
+    assert_eq!(breaker.position(), 11);
+  }
+
+  quickcheck! {
+    fn lines_fit_and_keep_every_letter(words: Vec<(u8, u8)>, max: u8) -> bool {
+      // words of 1 to 12 letters, with 1 to 3 spaces between them
+      let mut text = Vec::new();
+      for (len, gap) in words {
+        text.extend((0..len % 12 + 1).map(|i| b'a' + i));
+        text.extend((0..gap % 3 + 1).map(|_| b' '));
+      }
+      let max_width = u32::from(max % 20) + 2;
+      let mut letters = Vec::new();
+      for line in LineBreaker::new(&text, max_width, |_| 1) {
+        if line.width > max_width {
+          return false;
+        }
+        letters.extend(line.text.iter().copied().filter(|&b| b != b' '));
+      }
+      letters == text.into_iter().filter(|&b| b != b' ').collect::<Vec<_>>()
+    }
+  }
+}
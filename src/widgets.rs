@@ -0,0 +1,672 @@
+//! Menus and other UI widgets drawn with a tile font.
+
+use crate::{
+  char_to_cp437,
+  gba::{Keys, TextScreenEntry, TextScreenblock},
+};
+
+/// A rectangle of tiles within a screenblock.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TileRect {
+  pub x: usize,
+  pub y: usize,
+  pub w: usize,
+  pub h: usize,
+}
+impl TileRect {
+  #[inline]
+  #[must_use]
+  pub const fn new(x: usize, y: usize, w: usize, h: usize) -> Self {
+    Self { x, y, w, h }
+  }
+  /// The center, in half tiles (so that it's always a whole number).
+  #[inline]
+  #[must_use]
+  const fn center2(self) -> (isize, isize) {
+    ((self.x * 2 + self.w) as isize, (self.y * 2 + self.h) as isize)
+  }
+}
+
+/// Draws glyphs into a screenblock for widgets.
+///
+/// Each glyph is drawn with tile `font_offset + byte`. Widgets draw normally
+/// with `palbank`, and draw the focused part of themselves with `highlight`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TilePainter {
+  screenblock: TextScreenblock,
+  font_offset: u16,
+  palbank: u16,
+  highlight: u16,
+}
+impl TilePainter {
+  #[inline]
+  #[must_use]
+  pub const fn new(screenblock: TextScreenblock) -> Self {
+    Self { screenblock, font_offset: 0, palbank: 0, highlight: 1 }
+  }
+  /// Sets the tile index of the font's first glyph.
+  #[inline]
+  #[must_use]
+  pub const fn with_font_offset(self, font_offset: u16) -> Self {
+    Self { font_offset, ..self }
+  }
+  /// Sets the normal and highlight palbanks.
+  #[inline]
+  #[must_use]
+  pub const fn with_palbanks(self, normal: u16, highlight: u16) -> Self {
+    Self { palbank: normal & 0xF, highlight: highlight & 0xF, ..self }
+  }
+
+  /// Draws one glyph. Positions off the screenblock are skipped.
+  #[inline]
+  pub fn put(&self, x: usize, y: usize, byte: u8, highlight: bool) {
+    if x < 32 && y < 32 {
+      let palbank = if highlight { self.highlight } else { self.palbank };
+      let id = self.font_offset.wrapping_add(byte as u16);
+      self
+        .screenblock
+        .as_volblock()
+        .index(y * 32 + x)
+        .write(TextScreenEntry::from_id_bank(id, palbank));
+    }
+  }
+  /// Draws text (translated to CP437), padding it with spaces to `width`
+  /// glyphs, or cutting it off there.
+  #[inline]
+  pub fn text(
+    &self, x: usize, y: usize, text: &str, width: usize, highlight: bool,
+  ) {
+    let mut chars = text.chars().map(|c| char_to_cp437(c).unwrap_or(b'?'));
+    for i in 0..width {
+      self.put(x + i, y, chars.next().unwrap_or(b' '), highlight);
+    }
+  }
+  /// Fills a rectangle with one glyph.
+  #[inline]
+  pub fn fill(&self, rect: TileRect, byte: u8, highlight: bool) {
+    for y in rect.y..(rect.y + rect.h) {
+      for x in rect.x..(rect.x + rect.w) {
+        self.put(x, y, byte, highlight);
+      }
+    }
+  }
+}
+
+/// Something a widget did that the game might care about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WidgetEvent {
+  /// An item in a [`List`] or [`Grid`] was picked with A.
+  Activated(usize),
+  /// A [`Checkbox`] was toggled, and this is its new state.
+  Toggled(bool),
+  /// A [`Slider`] moved, and this is its new value.
+  Changed(i32),
+  /// A [`TextField`] finished being edited.
+  Submitted,
+  /// B was pressed and the widget didn't use it.
+  Back,
+}
+
+/// How a widget handled some key presses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Response {
+  /// The widget didn't use the keys, so the D-pad can move the focus.
+  Ignored,
+  /// The widget used the keys, and needs to be drawn again.
+  Consumed,
+  /// The widget used the keys and has an event.
+  Event(WidgetEvent),
+}
+
+/// A piece of UI that's drawn in a screenblock and can have the focus.
+pub trait Widget {
+  /// Where the widget is drawn.
+  fn rect(&self) -> TileRect;
+  /// If the widget can take the focus.
+  fn is_focusable(&self) -> bool {
+    true
+  }
+  /// Handles newly pressed keys while the widget has the focus.
+  fn handle(&mut self, pressed: Keys) -> Response;
+  /// Draws the whole widget.
+  fn draw(&self, painter: &TilePainter, focused: bool);
+}
+
+/// A frame drawn with CP437 double line characters, with an optional title
+/// in the top edge.
+///
+/// Frames never take the focus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+  pub rect: TileRect,
+  pub title: Option<&'static str>,
+}
+impl Frame {
+  #[inline]
+  #[must_use]
+  pub const fn new(rect: TileRect) -> Self {
+    Self { rect, title: None }
+  }
+  #[inline]
+  #[must_use]
+  pub const fn with_title(self, title: &'static str) -> Self {
+    Self { title: Some(title), ..self }
+  }
+}
+impl Widget for Frame {
+  #[inline]
+  fn rect(&self) -> TileRect {
+    self.rect
+  }
+  #[inline]
+  fn is_focusable(&self) -> bool {
+    false
+  }
+  #[inline]
+  fn handle(&mut self, _pressed: Keys) -> Response {
+    Response::Ignored
+  }
+  fn draw(&self, painter: &TilePainter, _focused: bool) {
+    let TileRect { x, y, w, h } = self.rect;
+    if w < 2 || h < 2 {
+      return;
+    }
+    let (right, bottom) = (x + w - 1, y + h - 1);
+    painter.put(x, y, 0xC9, false);
+    painter.put(right, y, 0xBB, false);
+    painter.put(x, bottom, 0xC8, false);
+    painter.put(right, bottom, 0xBC, false);
+    painter.fill(TileRect::new(x + 1, y, w - 2, 1), 0xCD, false);
+    painter.fill(TileRect::new(x + 1, bottom, w - 2, 1), 0xCD, false);
+    painter.fill(TileRect::new(x, y + 1, 1, h - 2), 0xBA, false);
+    painter.fill(TileRect::new(right, y + 1, 1, h - 2), 0xBA, false);
+    painter.fill(TileRect::new(x + 1, y + 1, w - 2, h - 2), b' ', false);
+    if let Some(title) = self.title {
+      let len = title.chars().count().min(w.saturating_sub(4));
+      painter.text(x + 2, y, title, len, false);
+    }
+  }
+}
+
+/// A vertical list of items, one per row, that scrolls if there are more
+/// items than rows.
+///
+/// Up and Down move the selection, and A activates the selected item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct List {
+  pub rect: TileRect,
+  items: &'static [&'static str],
+  selected: usize,
+  scroll: usize,
+}
+impl List {
+  #[inline]
+  #[must_use]
+  pub const fn new(rect: TileRect, items: &'static [&'static str]) -> Self {
+    Self { rect, items, selected: 0, scroll: 0 }
+  }
+  #[inline]
+  #[must_use]
+  pub const fn selected(&self) -> usize {
+    self.selected
+  }
+  /// Selects an item, scrolling so that it can be seen.
+  #[inline]
+  pub fn select(&mut self, i: usize) {
+    self.selected = i.min(self.items.len().saturating_sub(1));
+    if self.selected < self.scroll {
+      self.scroll = self.selected;
+    } else if self.selected >= self.scroll + self.rect.h {
+      self.scroll = self.selected + 1 - self.rect.h;
+    }
+  }
+}
+impl Widget for List {
+  #[inline]
+  fn rect(&self) -> TileRect {
+    self.rect
+  }
+  fn handle(&mut self, pressed: Keys) -> Response {
+    if pressed.a() && !self.items.is_empty() {
+      Response::Event(WidgetEvent::Activated(self.selected))
+    } else if pressed.up() && self.selected > 0 {
+      self.select(self.selected - 1);
+      Response::Consumed
+    } else if pressed.down() && self.selected + 1 < self.items.len() {
+      self.select(self.selected + 1);
+      Response::Consumed
+    } else {
+      Response::Ignored
+    }
+  }
+  fn draw(&self, painter: &TilePainter, focused: bool) {
+    let TileRect { x, y, w, h } = self.rect;
+    for row in 0..h {
+      let i = self.scroll + row;
+      let highlight = focused && i == self.selected;
+      let item = self.items.get(i).copied().unwrap_or("");
+      let marker =
+        if i == self.selected && i < self.items.len() { 0x10 } else { b' ' };
+      painter.put(x, y + row, marker, highlight);
+      painter.text(x + 1, y + row, item, w.saturating_sub(1), highlight);
+    }
+  }
+}
+
+/// Items laid out in rows of `columns` cells, each `cell_width` tiles wide.
+///
+/// The D-pad moves the selection, and A activates the selected item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grid {
+  pub x: usize,
+  pub y: usize,
+  items: &'static [&'static str],
+  columns: usize,
+  cell_width: usize,
+  selected: usize,
+}
+impl Grid {
+  /// ## Panics
+  /// * If `columns` is 0.
+  #[inline]
+  #[must_use]
+  pub const fn new(
+    x: usize, y: usize, items: &'static [&'static str], columns: usize,
+    cell_width: usize,
+  ) -> Self {
+    assert!(columns > 0, "a grid needs at least one column");
+    Self { x, y, items, columns, cell_width, selected: 0 }
+  }
+  #[inline]
+  #[must_use]
+  pub const fn selected(&self) -> usize {
+    self.selected
+  }
+}
+impl Widget for Grid {
+  #[inline]
+  fn rect(&self) -> TileRect {
+    let rows = self.items.len().div_ceil(self.columns);
+    TileRect::new(self.x, self.y, self.columns * self.cell_width, rows)
+  }
+  fn handle(&mut self, pressed: Keys) -> Response {
+    let (col, len) = (self.selected % self.columns, self.items.len());
+    let next = if pressed.a() && len > 0 {
+      return Response::Event(WidgetEvent::Activated(self.selected));
+    } else if pressed.left() && col > 0 {
+      self.selected - 1
+    } else if pressed.right()
+      && col + 1 < self.columns
+      && self.selected + 1 < len
+    {
+      self.selected + 1
+    } else if pressed.up() && self.selected >= self.columns {
+      self.selected - self.columns
+    } else if pressed.down() && self.selected + self.columns < len {
+      self.selected + self.columns
+    } else {
+      return Response::Ignored;
+    };
+    self.selected = next;
+    Response::Consumed
+  }
+  fn draw(&self, painter: &TilePainter, focused: bool) {
+    let rect = self.rect();
+    for cell in 0..(rect.h * self.columns) {
+      let x = self.x + (cell % self.columns) * self.cell_width;
+      let y = self.y + cell / self.columns;
+      let item = self.items.get(cell).copied().unwrap_or("");
+      let highlight = focused && cell == self.selected;
+      painter.text(x, y, item, self.cell_width, highlight);
+    }
+  }
+}
+
+/// A box that can be checked or not, with a label after it.
+///
+/// A toggles it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkbox {
+  pub x: usize,
+  pub y: usize,
+  pub label: &'static str,
+  pub checked: bool,
+}
+impl Checkbox {
+  #[inline]
+  #[must_use]
+  pub const fn new(x: usize, y: usize, label: &'static str) -> Self {
+    Self { x, y, label, checked: false }
+  }
+}
+impl Widget for Checkbox {
+  #[inline]
+  fn rect(&self) -> TileRect {
+    TileRect::new(self.x, self.y, 4 + self.label.chars().count(), 1)
+  }
+  fn handle(&mut self, pressed: Keys) -> Response {
+    if pressed.a() {
+      self.checked = !self.checked;
+      Response::Event(WidgetEvent::Toggled(self.checked))
+    } else {
+      Response::Ignored
+    }
+  }
+  fn draw(&self, painter: &TilePainter, focused: bool) {
+    let mark = if self.checked { 0xFB } else { b' ' };
+    painter.put(self.x, self.y, b'[', focused);
+    painter.put(self.x + 1, self.y, mark, focused);
+    painter.put(self.x + 2, self.y, b']', focused);
+    painter.put(self.x + 3, self.y, b' ', false);
+    painter.text(self.x + 4, self.y, self.label, self.rect().w - 4, false);
+  }
+}
+
+/// A value between `min` and `max`, shown as a track with a knob on it.
+///
+/// Left and Right change the value by `step`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slider {
+  pub x: usize,
+  pub y: usize,
+  pub width: usize,
+  min: i32,
+  max: i32,
+  step: i32,
+  value: i32,
+}
+impl Slider {
+  /// ## Panics
+  /// * If `min` is more than `max`, or `width` is less than 2.
+  #[inline]
+  #[must_use]
+  pub const fn new(
+    x: usize, y: usize, width: usize, min: i32, max: i32,
+  ) -> Self {
+    assert!(min <= max, "slider min must not be more than max");
+    assert!(width >= 2, "slider must be at least 2 tiles wide");
+    Self { x, y, width, min, max, step: 1, value: min }
+  }
+  #[inline]
+  #[must_use]
+  pub const fn with_step(self, step: i32) -> Self {
+    Self { step, ..self }
+  }
+  #[inline]
+  #[must_use]
+  pub const fn value(&self) -> i32 {
+    self.value
+  }
+  /// Sets the value, clamped to the slider's range.
+  #[inline]
+  pub fn set_value(&mut self, value: i32) {
+    self.value = value.clamp(self.min, self.max);
+  }
+}
+impl Widget for Slider {
+  #[inline]
+  fn rect(&self) -> TileRect {
+    TileRect::new(self.x, self.y, self.width, 1)
+  }
+  fn handle(&mut self, pressed: Keys) -> Response {
+    let old = self.value;
+    if pressed.left() {
+      self.set_value(self.value.saturating_sub(self.step));
+    } else if pressed.right() {
+      self.set_value(self.value.saturating_add(self.step));
+    } else {
+      return Response::Ignored;
+    }
+    if self.value == old {
+      Response::Consumed
+    } else {
+      Response::Event(WidgetEvent::Changed(self.value))
+    }
+  }
+  fn draw(&self, painter: &TilePainter, focused: bool) {
+    let range = (self.max - self.min) as i64;
+    let knob = if range == 0 {
+      0
+    } else {
+      ((self.value - self.min) as i64 * (self.width as i64 - 1) / range)
+        as usize
+    };
+    for i in 0..self.width {
+      let byte = if i == knob { 0xDB } else { 0xC4 };
+      painter.put(self.x + i, self.y, byte, focused && i == knob);
+    }
+  }
+}
+
+/// A field of up to `N` printable ASCII characters, edited with the D-pad.
+///
+/// A starts editing. While editing, Up and Down change the character at the
+/// cursor, Left and Right move the cursor, A moves on to the next character,
+/// B deletes the character before the cursor, and Start finishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextField<const N: usize> {
+  pub x: usize,
+  pub y: usize,
+  buf: [u8; N],
+  len: usize,
+  cursor: usize,
+  editing: bool,
+}
+impl<const N: usize> TextField<N> {
+  /// ## Panics
+  /// * If `N` is 0.
+  #[inline]
+  #[must_use]
+  pub const fn new(x: usize, y: usize) -> Self {
+    assert!(N > 0, "a text field needs room for at least one character");
+    Self { x, y, buf: [0; N], len: 0, cursor: 0, editing: false }
+  }
+  /// The field's text.
+  #[inline]
+  #[must_use]
+  pub fn text(&self) -> &str {
+    // only printable ASCII is ever put into the buffer
+    core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+  }
+  #[inline]
+  #[must_use]
+  pub const fn is_editing(&self) -> bool {
+    self.editing
+  }
+
+  /// Steps the character at the cursor through printable ASCII.
+  fn cycle(&mut self, up: bool) {
+    if self.cursor == self.len {
+      if self.len == N {
+        return;
+      }
+      self.buf[self.len] = b' ';
+      self.len += 1;
+    }
+    let byte = &mut self.buf[self.cursor];
+    *byte = match (up, *byte) {
+      (true, b'~') => b' ',
+      (true, b) => b + 1,
+      (false, b' ') => b'~',
+      (false, b) => b - 1,
+    };
+  }
+}
+impl<const N: usize> Widget for TextField<N> {
+  #[inline]
+  fn rect(&self) -> TileRect {
+    TileRect::new(self.x, self.y, N + 1, 1)
+  }
+  fn handle(&mut self, pressed: Keys) -> Response {
+    if !self.editing {
+      if pressed.a() {
+        self.editing = true;
+        self.cursor = self.len.min(N - 1);
+        return Response::Consumed;
+      }
+      return Response::Ignored;
+    }
+    if pressed.start() {
+      self.editing = false;
+      return Response::Event(WidgetEvent::Submitted);
+    }
+    if pressed.up() {
+      self.cycle(true);
+    } else if pressed.down() {
+      self.cycle(false);
+    } else if pressed.left() {
+      self.cursor = self.cursor.saturating_sub(1);
+    } else if pressed.right() || pressed.a() {
+      if self.cursor == self.len && self.len < N {
+        self.buf[self.len] = b'a';
+        self.len += 1;
+      }
+      self.cursor = (self.cursor + 1).min(self.len).min(N - 1);
+    } else if pressed.b() && self.cursor > 0 {
+      self.buf.copy_within(self.cursor..self.len, self.cursor - 1);
+      self.len -= 1;
+      self.cursor -= 1;
+    }
+    Response::Consumed
+  }
+  fn draw(&self, painter: &TilePainter, focused: bool) {
+    for i in 0..=N {
+      let byte = if i < self.len {
+        self.buf[i]
+      } else if i < N {
+        b'_'
+      } else {
+        b' '
+      };
+      let highlight = focused && (!self.editing || i == self.cursor);
+      painter.put(self.x + i, self.y, byte, highlight);
+    }
+  }
+}
+
+/// Which widget in a set has the focus.
+///
+/// The group gives keys to the focused widget, and if the widget ignores
+/// them the D-pad moves the focus to the nearest focusable widget in that
+/// direction. The widgets themselves are passed in each time, usually as an
+/// array of `&mut dyn Widget`, so the group and the widgets can be kept
+/// wherever is convenient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FocusGroup {
+  focus: usize,
+  dirty: bool,
+}
+impl FocusGroup {
+  /// Makes a group with the focus on widget 0.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self { focus: 0, dirty: true }
+  }
+  /// The index of the focused widget.
+  #[inline]
+  #[must_use]
+  pub const fn focus(&self) -> usize {
+    self.focus
+  }
+  #[inline]
+  pub fn set_focus(&mut self, focus: usize) {
+    self.focus = focus;
+    self.dirty = true;
+  }
+  /// Makes the next [`draw`](Self::draw) redraw everything, such as after
+  /// changing a widget from outside the group.
+  #[inline]
+  pub fn mark_dirty(&mut self) {
+    self.dirty = true;
+  }
+
+  /// Handles newly pressed keys, giving back the index of the widget that
+  /// had an event along with the event.
+  pub fn update(
+    &mut self, widgets: &mut [&mut dyn Widget], pressed: Keys,
+  ) -> Option<(usize, WidgetEvent)> {
+    if !widgets.get(self.focus).is_some_and(|w| w.is_focusable()) {
+      self.focus = widgets.iter().position(|w| w.is_focusable())?;
+      self.dirty = true;
+    }
+    match widgets[self.focus].handle(pressed) {
+      Response::Consumed => {
+        self.dirty = true;
+        None
+      }
+      Response::Event(event) => {
+        self.dirty = true;
+        Some((self.focus, event))
+      }
+      Response::Ignored => {
+        if pressed.b() {
+          return Some((self.focus, WidgetEvent::Back));
+        }
+        let direction = if pressed.up() {
+          (0, -1)
+        } else if pressed.down() {
+          (0, 1)
+        } else if pressed.left() {
+          (-1, 0)
+        } else if pressed.right() {
+          (1, 0)
+        } else {
+          return None;
+        };
+        if let Some(next) = self.nearest(widgets, direction) {
+          self.focus = next;
+          self.dirty = true;
+        }
+        None
+      }
+    }
+  }
+
+  /// Draws all the widgets, if anything has changed since they were last
+  /// drawn.
+  ///
+  /// Widgets are drawn in order, so a [`Frame`] should come before the
+  /// widgets inside it.
+  ///
+  /// Call this during VBlank.
+  pub fn draw(&mut self, widgets: &[&mut dyn Widget], painter: &TilePainter) {
+    if !self.dirty {
+      return;
+    }
+    self.dirty = false;
+    for (i, widget) in widgets.iter().enumerate() {
+      widget.draw(painter, i == self.focus);
+    }
+  }
+
+  /// The closest focusable widget in a direction from the focused one.
+  fn nearest(
+    &self, widgets: &[&mut dyn Widget], (dx, dy): (isize, isize),
+  ) -> Option<usize> {
+    let (fx, fy) = widgets[self.focus].rect().center2();
+    widgets
+      .iter()
+      .enumerate()
+      .filter(|(i, w)| *i != self.focus && w.is_focusable())
+      .filter_map(|(i, w)| {
+        let (x, y) = w.rect().center2();
+        // distance along the direction, and off to the side of it
+        let ahead = (x - fx) * dx + (y - fy) * dy;
+        let aside = ((x - fx) * dy - (y - fy) * dx).abs();
+        if ahead > 0 {
+          Some((ahead + aside * 2, i))
+        } else {
+          None
+        }
+      })
+      .min()
+      .map(|(_, i)| i)
+  }
+}
+impl Default for FocusGroup {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
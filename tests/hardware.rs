@@ -0,0 +1,70 @@
+//! Tests of the parts that need real (or emulated) hardware.
+
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(zygravan::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use zygravan::{
+  gba::{critical_section, Color, GbaMutex, PalRam, IME},
+  SpscQueue,
+};
+
+#[no_mangle]
+extern "C" fn main() -> ! {
+  test_main();
+  zygravan::testing::exit(0)
+}
+
+#[panic_handler]
+fn panic_handler(info: &core::panic::PanicInfo) -> ! {
+  zygravan::testing::test_panic(info)
+}
+
+#[test_case]
+fn critical_section_restores_ime() {
+  for ime in [false, true] {
+    IME.write(ime);
+    critical_section(|_| {
+      assert!(!IME.read());
+      critical_section(|_| assert!(!IME.read()));
+      assert!(!IME.read());
+    });
+    assert_eq!(IME.read(), ime);
+  }
+  IME.write(false);
+}
+
+#[test_case]
+fn mutex_refuses_a_second_lock() {
+  static COUNTER: GbaMutex<u32> = GbaMutex::new(0);
+  COUNTER.lock(|n| *n += 1);
+  let inner = COUNTER.lock(|n| {
+    *n += 1;
+    COUNTER.try_lock(|_| ())
+  });
+  assert_eq!(inner, None);
+  assert_eq!(COUNTER.lock(|n| *n), 2);
+}
+
+#[test_case]
+fn spsc_queue_keeps_order() {
+  static QUEUE: SpscQueue<u16, 4> = SpscQueue::new();
+  for i in 0..4 {
+    assert_eq!(QUEUE.push(i), Ok(()));
+  }
+  assert_eq!(QUEUE.push(4), Err(4));
+  for i in 0..4 {
+    assert_eq!(QUEUE.pop(), Some(i));
+  }
+  assert_eq!(QUEUE.pop(), None);
+}
+
+#[test_case]
+fn palram_holds_colors() {
+  let backdrop = PalRam::backdrop();
+  backdrop.write(Color::from_rgb(1, 2, 3));
+  assert_eq!(backdrop.read().to_bits(), Color::from_rgb(1, 2, 3).to_bits());
+  backdrop.write(Color::BLACK);
+}
@@ -0,0 +1,103 @@
+#!/usr/bin/env python3
+"""The cargo runner for the GBA target.
+
+Normal programs (`cargo run`) are opened in the mGBA window as usual. Test
+ROMs (`cargo test`, which cargo builds into `target/.../deps/`) are run in a
+headless `mgba-rom-test` instead, and the test lines they write to mGBA's
+debug log (see `src/testing.rs`) are turned into test results.
+
+Environment variables:
+* `GBA_EMULATOR`: the emulator for normal programs (default `mgba`).
+* `GBA_TEST_EMULATOR`: the headless emulator for tests (default
+  `mgba-rom-test`). It's given `-S 0x27 -R r0` so that it exits when the ROM
+  calls `testing::exit`.
+* `GBA_TEST_TIMEOUT`: seconds before a test ROM is counted as hung (default
+  60).
+"""
+
+import os
+import re
+import subprocess
+import sys
+import threading
+
+# the exit SWI, which matches `testing::EXIT_SWI`.
+EXIT_SWI = "0x27"
+
+TEST_LINE = re.compile(r"(running \d+ tests|test .*)$")
+
+
+def is_test_rom(path):
+    return os.path.basename(os.path.dirname(os.path.abspath(path))) == "deps"
+
+
+def run_program(rom, args):
+    emulator = os.environ.get("GBA_EMULATOR", "mgba")
+    return subprocess.call([emulator, rom] + args)
+
+
+def run_tests(rom):
+    emulator = os.environ.get("GBA_TEST_EMULATOR", "mgba-rom-test")
+    timeout = float(os.environ.get("GBA_TEST_TIMEOUT", "60"))
+    proc = subprocess.Popen(
+        [emulator, "-S", EXIT_SWI, "-R", "r0", rom],
+        stdout=subprocess.PIPE,
+        stderr=subprocess.STDOUT,
+        universal_newlines=True,
+    )
+    # a hung ROM never prints anything else, so kill it from another thread.
+    timer = threading.Timer(timeout, proc.kill)
+    timer.start()
+    result = None
+    current = None
+    passed = 0
+    for line in proc.stdout:
+        # mGBA puts its own prefix in front of each debug message.
+        found = TEST_LINE.search(line.rstrip("\r\n"))
+        if found:
+            text = found.group(1)
+            if text.startswith("test result: "):
+                result = text
+                break
+            elif text.startswith("test failed: "):
+                print("test {} ... FAILED".format(current or "?"))
+                print("  " + text[len("test failed: "):])
+            elif text.endswith(" ... ok"):
+                print(text)
+                passed += 1
+                current = None
+            elif text.endswith(" ..."):
+                current = text[len("test "):-len(" ...")]
+            else:
+                print(text)
+    if proc.poll() is None:
+        proc.kill()
+    code = proc.wait()
+    timer.cancel()
+
+    if result is None:
+        if current is not None:
+            print("test {} ... FAILED".format(current))
+            print("  the ROM stopped without finishing this test")
+        else:
+            print("the ROM stopped without a test result")
+        print("\ntest result: FAILED. {} passed".format(passed))
+        return 1
+    print("\n" + result)
+    if result.startswith("test result: ok"):
+        return 0
+    return code or 1
+
+
+def main():
+    if len(sys.argv) < 2:
+        print("usage: gba_runner.py <rom> [args...]", file=sys.stderr)
+        return 2
+    rom = sys.argv[1]
+    if is_test_rom(rom):
+        return run_tests(rom)
+    return run_program(rom, sys.argv[2:])
+
+
+if __name__ == "__main__":
+    sys.exit(main())